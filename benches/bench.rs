@@ -87,6 +87,44 @@ fn fold(bencher: divan::Bencher<'_, '_>, context: usize) {
         });
 }
 
+#[divan::bench(args=[1_000, 10_000, 100_000, 1_000_000])]
+fn annotations_near_end(bencher: divan::Bencher<'_, '_>, lines: usize) {
+    bencher
+        .with_inputs(|| {
+            let line = "012345678901234567890123456789";
+            let mut input = String::new();
+            for _ in 1..=lines {
+                input.push_str(line);
+                input.push('\n');
+            }
+            let last_line = input.len() - line.len() - 1;
+            let spans = [
+                last_line - 2 * (line.len() + 1)..last_line - 2 * (line.len() + 1) + 5,
+                last_line..last_line + 10,
+            ];
+            (input, spans)
+        })
+        .bench_values(|(input, spans)| {
+            let message = &[Level::ERROR
+                .primary_title("mismatched types")
+                .id("E0308")
+                .element(
+                    Snippet::source(&input)
+                        .path("src/format.rs")
+                        .annotation(AnnotationKind::Context.span(spans[0].clone()))
+                        .annotation(
+                            AnnotationKind::Primary
+                                .span(spans[1].clone())
+                                .label("expected `Option<String>` because of return type"),
+                        ),
+                )];
+
+            let renderer = Renderer::plain();
+            let rendered = renderer.render(message);
+            rendered
+        });
+}
+
 fn main() {
     divan::main();
 }
@@ -0,0 +1,224 @@
+//! Sources that are not UTF-8, see [`Snippet::binary`]
+//!
+//! [`Snippet::binary`]: crate::Snippet::binary
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::cmp::{max, min};
+use core::fmt::Write as _;
+
+use crate::Annotation;
+
+const REPLACEMENT_LEN: usize = char::REPLACEMENT_CHARACTER.len_utf8();
+const BYTES_PER_ROW: usize = 16;
+
+/// How [`Snippet::binary`] shows its bytes
+///
+/// [`Snippet::binary`]: crate::Snippet::binary
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryView {
+    /// Lines of text, with invalid UTF-8 replaced by `�`, like [`String::from_utf8_lossy`]
+    Lossy,
+    /// Rows of 16 bytes, each with its offset, its bytes in hex and as ASCII, like
+    /// `hexdump -C`
+    ///
+    /// ```text
+    /// 00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|
+    /// ```
+    ///
+    /// An [`Annotation`] underlines its bytes in both the hex and the ASCII column.
+    Hexdump,
+}
+
+/// Where the bytes of a [`Snippet::binary`] are in its text
+///
+/// [`Snippet::binary`]: crate::Snippet::binary
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ByteMap {
+    Lossy {
+        /// The invalid sequences replaced by `�`, in order
+        replacements: Vec<Replacement>,
+    },
+    Hexdump {
+        /// The number of bytes
+        len: usize,
+        /// The number of hex digits in each offset
+        offset_width: usize,
+    },
+}
+
+/// An invalid UTF-8 sequence replaced by `�`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Replacement {
+    /// The byte offset of the sequence
+    byte: usize,
+    /// The length of the sequence in bytes
+    len: usize,
+    /// The byte offset of the `�` in the text
+    text: usize,
+}
+
+impl ByteMap {
+    /// The text to show for `bytes`, along with where each byte is in it
+    pub(crate) fn new(bytes: &[u8], view: BinaryView) -> (String, Self) {
+        match view {
+            BinaryView::Lossy => {
+                let mut text = String::with_capacity(bytes.len());
+                let mut replacements = vec![];
+                let mut byte = 0;
+                for chunk in bytes.utf8_chunks() {
+                    text.push_str(chunk.valid());
+                    byte += chunk.valid().len();
+                    if !chunk.invalid().is_empty() {
+                        replacements.push(Replacement {
+                            byte,
+                            len: chunk.invalid().len(),
+                            text: text.len(),
+                        });
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        byte += chunk.invalid().len();
+                    }
+                }
+                (text, Self::Lossy { replacements })
+            }
+            BinaryView::Hexdump => {
+                let last_offset = bytes.len().saturating_sub(1) / BYTES_PER_ROW * BYTES_PER_ROW;
+                let offset_width = max(8, format!("{last_offset:x}").len());
+                let mut text = String::new();
+                for (row, chunk) in bytes.chunks(BYTES_PER_ROW).enumerate() {
+                    if row != 0 {
+                        text.push('\n');
+                    }
+                    write!(text, "{:0offset_width$x}  ", row * BYTES_PER_ROW).unwrap();
+                    for i in 0..BYTES_PER_ROW {
+                        if i == BYTES_PER_ROW / 2 {
+                            text.push(' ');
+                        }
+                        match chunk.get(i) {
+                            Some(byte) => write!(text, "{byte:02x} ").unwrap(),
+                            None => text.push_str("   "),
+                        }
+                    }
+                    text.push_str(" |");
+                    text.extend(chunk.iter().map(|&byte| {
+                        if byte.is_ascii_graphic() || byte == b' ' {
+                            char::from(byte)
+                        } else {
+                            '.'
+                        }
+                    }));
+                    text.push('|');
+                }
+                let len = bytes.len();
+                (text, Self::Hexdump { len, offset_width })
+            }
+        }
+    }
+
+    /// `annotation`, whose span is into the bytes, as annotations of the text
+    pub(crate) fn annotations<'a>(&self, annotation: Annotation<'a>) -> Vec<Annotation<'a>> {
+        let span = annotation.span.clone();
+        match self {
+            Self::Lossy { replacements } => {
+                let start = lossy_offset(replacements, span.start, false);
+                let end = lossy_offset(replacements, span.end, true);
+                vec![Annotation {
+                    span: start..max(start, end),
+                    ..annotation
+                }]
+            }
+            Self::Hexdump { len, offset_width } => {
+                let layout = HexdumpLayout {
+                    offset_width: *offset_width,
+                };
+                if span.start >= span.end || span.start >= *len {
+                    let point = if *len == 0 {
+                        0
+                    } else if span.start >= *len {
+                        // Just after the last byte
+                        let last = len - 1;
+                        layout.row_start(last / BYTES_PER_ROW)
+                            + layout.hex(last % BYTES_PER_ROW + 1)
+                    } else {
+                        layout.row_start(span.start / BYTES_PER_ROW)
+                            + layout.hex(span.start % BYTES_PER_ROW)
+                    };
+                    return vec![Annotation {
+                        span: point..point,
+                        ..annotation
+                    }];
+                }
+
+                let end = min(span.end, *len);
+                let mut annotations = vec![];
+                for row in span.start / BYTES_PER_ROW..=(end - 1) / BYTES_PER_ROW {
+                    let row_start = layout.row_start(row);
+                    let first = max(span.start, row * BYTES_PER_ROW) % BYTES_PER_ROW;
+                    let last = (min(end, (row + 1) * BYTES_PER_ROW) - 1) % BYTES_PER_ROW;
+                    // Only the first part is labeled
+                    let label = if annotations.is_empty() {
+                        annotation.label.clone()
+                    } else {
+                        None
+                    };
+                    annotations.push(Annotation {
+                        span: row_start + layout.hex(first)..row_start + layout.hex(last) + 2,
+                        label,
+                        ..annotation.clone()
+                    });
+                    annotations.push(Annotation {
+                        span: row_start + layout.ascii(first)..row_start + layout.ascii(last) + 1,
+                        label: None,
+                        ..annotation.clone()
+                    });
+                }
+                annotations
+            }
+        }
+    }
+}
+
+/// The offset in the text of `byte`, rounding down, or up with `round_up`, when it is within
+/// a replaced sequence
+fn lossy_offset(replacements: &[Replacement], byte: usize, round_up: bool) -> usize {
+    let i = replacements.partition_point(|r| r.byte <= byte);
+    let Some(r) = i.checked_sub(1).map(|i| replacements[i]) else {
+        return byte;
+    };
+    if byte < r.byte + r.len {
+        if round_up && r.byte < byte {
+            r.text + REPLACEMENT_LEN
+        } else {
+            r.text
+        }
+    } else {
+        r.text + REPLACEMENT_LEN + byte - (r.byte + r.len)
+    }
+}
+
+/// The columns of a [`BinaryView::Hexdump`] row
+struct HexdumpLayout {
+    offset_width: usize,
+}
+
+impl HexdumpLayout {
+    /// The length of a full row, including its line ending
+    fn row_len(&self) -> usize {
+        self.ascii(BYTES_PER_ROW) + 2
+    }
+
+    fn row_start(&self, row: usize) -> usize {
+        row * self.row_len()
+    }
+
+    /// The column of the `i`th byte of a row in hex
+    fn hex(&self, i: usize) -> usize {
+        self.offset_width + 2 + 3 * i + usize::from(i >= BYTES_PER_ROW / 2)
+    }
+
+    /// The column of the `i`th byte of a row as ASCII
+    fn ascii(&self, i: usize) -> usize {
+        self.hex(BYTES_PER_ROW) + 2 + i
+    }
+}
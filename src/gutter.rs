@@ -0,0 +1,64 @@
+//! [`LineLabels`] for showing something other than line numbers in the gutter
+
+use alloc::format;
+use alloc::string::String;
+
+/// The labels shown in the gutter of a [`Snippet`] in place of line numbers, see
+/// [`Snippet::line_labels`]
+///
+/// The gutter is as wide as the widest label or line number of any [`Snippet`] in the
+/// [`Report`], so all of them line up.
+///
+/// # Example
+///
+/// ```rust
+/// # use annotate_snippets::gutter::LineLabels;
+/// /// Byte offsets of each line
+/// #[derive(Debug)]
+/// struct Offsets<'a> {
+///     source: &'a str,
+/// }
+///
+/// impl LineLabels for Offsets<'_> {
+///     fn label(&self, line: usize) -> String {
+///         let offset: usize = self.source.split_inclusive('\n').take(line - 1).map(str::len).sum();
+///         format!("{offset:#06x}")
+///     }
+/// }
+/// ```
+///
+/// [`Snippet`]: crate::Snippet
+/// [`Snippet::line_labels`]: crate::Snippet::line_labels
+/// [`Report`]: crate::Report
+pub trait LineLabels: core::fmt::Debug + Sync {
+    /// The label of the (1-based) line `line`, counted from [`Snippet::line_start`]
+    ///
+    /// [`Snippet::line_start`]: crate::Snippet::line_start
+    fn label(&self, line: usize) -> String;
+}
+
+/// Line numbers after a prefix, e.g. `In[3]:2` for a notebook cell or `tmpl:12` for a template
+///
+/// # Example
+///
+/// ```rust
+/// # use annotate_snippets::gutter::Prefixed;
+/// const CELL: Prefixed<'_> = Prefixed::new("In[3]:");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Prefixed<'a> {
+    prefix: &'a str,
+}
+
+impl<'a> Prefixed<'a> {
+    /// Show `prefix` before each line number
+    pub const fn new(prefix: &'a str) -> Self {
+        Self { prefix }
+    }
+}
+
+impl LineLabels for Prefixed<'_> {
+    fn label(&self, line: usize) -> String {
+        format!("{}{line}", self.prefix)
+    }
+}
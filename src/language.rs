@@ -0,0 +1,97 @@
+//! [`LanguageProfile`]s for shortening multiline [`Annotation`]s
+//!
+//! [`Annotation`]: crate::Annotation
+
+/// Language-specific knowledge about a [`Snippet`]'s source, see [`Snippet::language`]
+///
+/// A multiline [`Annotation`] only shows its first few lines (see
+/// [`Snippet::multiline_lines`]) before jumping to its end. The shown lines stop early at lines
+/// that carry no information, like blank lines, comments or lone delimiters.
+///
+/// # Example
+///
+/// ```rust
+/// # use annotate_snippets::language::LanguageProfile;
+/// /// YAML, where comments and document markers carry no information
+/// #[derive(Debug)]
+/// struct Yaml;
+///
+/// impl LanguageProfile for Yaml {
+///     fn is_insignificant(&self, line: &str) -> bool {
+///         let line = line.trim();
+///         line.is_empty() || line.starts_with('#') || line == "---" || line == "..."
+///     }
+/// }
+/// ```
+///
+/// [`Snippet`]: crate::Snippet
+/// [`Snippet::language`]: crate::Snippet::language
+/// [`Snippet::multiline_lines`]: crate::Snippet::multiline_lines
+/// [`Annotation`]: crate::Annotation
+pub trait LanguageProfile: core::fmt::Debug + Sync {
+    /// Whether `line` carries no information, e.g. it is blank, a comment or a lone delimiter
+    fn is_insignificant(&self, line: &str) -> bool;
+}
+
+/// Rust, the default [`LanguageProfile`]
+///
+/// Blank lines, comments that are not doc comments, and lines with a single delimiter are
+/// insignificant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rust;
+
+impl LanguageProfile for Rust {
+    fn is_insignificant(&self, line: &str) -> bool {
+        let line = line.trim();
+        // Consider comments as empty, but don't consider docstrings to be empty.
+        (line.starts_with("//") && !(line.starts_with("///") || line.starts_with("//!")))
+            // Consider lines with nothing but whitespace, a single delimiter as empty.
+            || ["", "{", "}", "(", ")", "[", "]"].contains(&line)
+    }
+}
+
+/// A [`LanguageProfile`] for languages with line comments
+///
+/// Blank lines, lines starting with one of the [comment markers][Self::new] and lines that are
+/// one of the [delimiters][Self::delimiters] are insignificant.
+///
+/// # Example
+///
+/// ```rust
+/// # use annotate_snippets::language::LineComments;
+/// const PYTHON: LineComments<'_> = LineComments::new(&["#"]).delimiters(&["(", ")", "[", "]"]);
+/// const SQL: LineComments<'_> = LineComments::new(&["--"]).delimiters(&["(", ")", ";"]);
+/// const YAML: LineComments<'_> = LineComments::new(&["#"]);
+/// // Only blank lines are insignificant
+/// const PLAIN_TEXT: LineComments<'_> = LineComments::new(&[]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineComments<'a> {
+    comments: &'a [&'a str],
+    delimiters: &'a [&'a str],
+}
+
+impl<'a> LineComments<'a> {
+    /// Lines starting with any of `comments`, after leading whitespace, are insignificant
+    pub const fn new(comments: &'a [&'a str]) -> Self {
+        Self {
+            comments,
+            delimiters: &[],
+        }
+    }
+
+    /// Lines with nothing but one of `delimiters` are insignificant
+    pub const fn delimiters(mut self, delimiters: &'a [&'a str]) -> Self {
+        self.delimiters = delimiters;
+        self
+    }
+}
+
+impl LanguageProfile for LineComments<'_> {
+    fn is_insignificant(&self, line: &str) -> bool {
+        let line = line.trim();
+        line.is_empty()
+            || self.comments.iter().any(|c| line.starts_with(c))
+            || self.delimiters.contains(&line)
+    }
+}
@@ -107,9 +107,13 @@ extern crate alloc;
 
 use alloc::string::String;
 
+pub mod binary;
+pub mod gutter;
+pub mod language;
 pub mod level;
 pub mod renderer;
 mod snippet;
+pub mod source;
 
 /// Normalize the string to avoid any unicode control characters.
 ///
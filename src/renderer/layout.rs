@@ -0,0 +1,168 @@
+//! Lay out a [`Report`] as styled text for custom backends, see [`Renderer::render_styled`]
+//! and [`Renderer::measure`]
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{ElementStyle, Renderer};
+use crate::Report;
+use crate::level::LevelInner;
+use crate::renderer::render::render_buffers;
+
+/// A [`Group`][crate::Group] laid out by [`Renderer::render_styled`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StyledGroup {
+    /// The severity of the group's primary [`Level`][crate::Level], which
+    /// [`SemanticStyle::UnderlinePrimary`] and [`SemanticStyle::LabelPrimary`] take their color
+    /// from
+    pub severity: Severity,
+    /// Each line as runs of text that share the same [`SemanticStyle`]
+    pub lines: Vec<Vec<StyledRun>>,
+}
+
+/// The size of a [`Group`][crate::Group] as shown by [`Renderer::render`], see
+/// [`Renderer::measure`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GroupSize {
+    /// The number of lines
+    pub rows: usize,
+    /// The display width of the widest line
+    pub columns: usize,
+}
+
+/// Text that is shown in one [`SemanticStyle`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StyledRun {
+    /// The text, as wide as its [`char`]s are in a terminal
+    pub text: String,
+    /// How the text is styled
+    pub style: SemanticStyle,
+}
+
+/// What a [`StyledRun`] is part of, which [`Renderer::render`] picks its [`Style`] by
+///
+/// [`Style`]: super::Style
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum SemanticStyle {
+    /// A [`Level::primary_title`][crate::Level::primary_title], see [`Renderer::emphasis`]
+    MainHeaderMsg,
+    /// A [`Level::secondary_title`][crate::Level::secondary_title], see [`Renderer::none`]
+    ///
+    /// Its text may carry its own ANSI escape codes.
+    HeaderMsg,
+    /// The path, line and column of a [`Snippet`][crate::Snippet], see [`Renderer::none`]
+    LineAndColumn,
+    /// Line numbers, gutters and other decorations, see [`Renderer::line_num`]
+    LineNumber,
+    /// Source code, see [`Renderer::none`]
+    Quotation,
+    /// The underline of a primary [`Annotation`][crate::Annotation], in the group's
+    /// [`Severity`]
+    UnderlinePrimary,
+    /// The underline of any other [`Annotation`][crate::Annotation], see [`Renderer::context`]
+    UnderlineSecondary,
+    /// The label of a primary [`Annotation`][crate::Annotation], in the group's [`Severity`]
+    LabelPrimary,
+    /// The label of any other [`Annotation`][crate::Annotation], see [`Renderer::context`]
+    LabelSecondary,
+    /// Other text and padding, see [`Renderer::none`]
+    NoStyle,
+    /// The name of a [`Level`][crate::Level], e.g. `error`
+    Level(Severity),
+    /// Code added by a [`Patch`][crate::Patch], see [`Renderer::addition`]
+    Addition,
+    /// Code removed by a [`Patch`][crate::Patch], see [`Renderer::removal`]
+    Removal,
+}
+
+/// The kind of a [`Level`][crate::Level], regardless of its name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Severity {
+    /// [`Level::ERROR`][crate::Level::ERROR], see [`Renderer::error`]
+    Error,
+    /// [`Level::WARNING`][crate::Level::WARNING], see [`Renderer::warning`]
+    Warning,
+    /// [`Level::INFO`][crate::Level::INFO], see [`Renderer::info`]
+    Info,
+    /// [`Level::NOTE`][crate::Level::NOTE], see [`Renderer::note`]
+    Note,
+    /// [`Level::HELP`][crate::Level::HELP], see [`Renderer::help`]
+    Help,
+}
+
+impl From<LevelInner> for Severity {
+    fn from(level: LevelInner) -> Self {
+        match level {
+            LevelInner::Error => Self::Error,
+            LevelInner::Warning => Self::Warning,
+            LevelInner::Info => Self::Info,
+            LevelInner::Note => Self::Note,
+            LevelInner::Help => Self::Help,
+        }
+    }
+}
+
+impl From<ElementStyle> for SemanticStyle {
+    fn from(style: ElementStyle) -> Self {
+        match style {
+            ElementStyle::MainHeaderMsg => Self::MainHeaderMsg,
+            ElementStyle::HeaderMsg => Self::HeaderMsg,
+            ElementStyle::LineAndColumn => Self::LineAndColumn,
+            ElementStyle::LineNumber => Self::LineNumber,
+            ElementStyle::Quotation => Self::Quotation,
+            ElementStyle::UnderlinePrimary => Self::UnderlinePrimary,
+            ElementStyle::UnderlineSecondary => Self::UnderlineSecondary,
+            ElementStyle::LabelPrimary => Self::LabelPrimary,
+            ElementStyle::LabelSecondary => Self::LabelSecondary,
+            ElementStyle::NoStyle => Self::NoStyle,
+            ElementStyle::Level(level) => Self::Level(level.into()),
+            ElementStyle::Addition => Self::Addition,
+            ElementStyle::Removal => Self::Removal,
+        }
+    }
+}
+
+pub(crate) fn measure(renderer: &Renderer, groups: Report<'_>) -> Vec<GroupSize> {
+    // Escape codes take no columns
+    let renderer = Renderer {
+        hyperlink: false,
+        ..renderer.clone()
+    };
+
+    render_buffers(&renderer, groups)
+        .iter()
+        .map(|(_, buffer)| GroupSize {
+            rows: buffer.num_lines(),
+            columns: buffer.max_width(renderer.width()),
+        })
+        .collect()
+}
+
+pub(crate) fn render_styled(renderer: &Renderer, groups: Report<'_>) -> Vec<StyledGroup> {
+    // Escape codes would end up as text in the runs
+    let renderer = Renderer {
+        hyperlink: false,
+        ..renderer.clone()
+    };
+
+    render_buffers(&renderer, groups)
+        .into_iter()
+        .map(|(level, buffer)| StyledGroup {
+            severity: level.level.into(),
+            lines: buffer
+                .runs(SemanticStyle::from)
+                .into_iter()
+                .map(|line| {
+                    line.into_iter()
+                        .map(|(style, text)| StyledRun { text, style })
+                        .collect()
+                })
+                .collect(),
+        })
+        .collect()
+}
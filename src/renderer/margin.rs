@@ -1,11 +1,89 @@
 use core::cmp::{max, min};
 
+// rustc's hard-coded trimming, measured from the leading whitespace minus the span padding
 const ELLIPSIS_PASSING: usize = 6;
 const LONG_WHITESPACE: usize = 20;
 const LONG_WHITESPACE_PADDING: usize = 4;
 
+/// How [`Renderer`] trims source lines wider than [`Renderer::term_width`], see
+/// [`Renderer::trim`]
+///
+/// [`Renderer`]: super::Renderer
+/// [`Renderer::term_width`]: super::Renderer::term_width
+/// [`Renderer::trim`]: super::Renderer::trim
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trim {
+    mode: TrimMode,
+    span_padding: usize,
+    long_whitespace: usize,
+    long_whitespace_padding: usize,
+}
+
+impl Trim {
+    /// Center the [`Annotation`][crate::Annotation]s and their labels, like rustc
+    pub const fn new() -> Self {
+        Self {
+            mode: TrimMode::Center,
+            span_padding: ELLIPSIS_PASSING,
+            // rustc trims when `whitespace - ELLIPSIS_PASSING > LONG_WHITESPACE`
+            long_whitespace: LONG_WHITESPACE + ELLIPSIS_PASSING,
+            // and keeps `LONG_WHITESPACE_PADDING` of the whitespace left after the span padding
+            long_whitespace_padding: LONG_WHITESPACE - LONG_WHITESPACE_PADDING + ELLIPSIS_PASSING,
+        }
+    }
+
+    /// Select which side of a line is trimmed
+    pub const fn mode(mut self, mode: TrimMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Columns kept around the [`Annotation`][crate::Annotation]s and their labels
+    ///
+    /// The default is `6`, leaving room for `...`.
+    pub const fn span_padding(mut self, columns: usize) -> Self {
+        self.span_padding = columns;
+        self
+    }
+
+    /// Trim the leading whitespace common to all lines when it is wider than `columns`, keeping
+    /// `padding` columns of it
+    ///
+    /// The first of the kept columns show `...`.
+    /// This applies to [`TrimMode::Center`] even when the lines fit.
+    /// The default is `long_whitespace(26, 22)`.
+    pub const fn long_whitespace(mut self, columns: usize, padding: usize) -> Self {
+        self.long_whitespace = columns;
+        self.long_whitespace_padding = padding;
+        self
+    }
+}
+
+impl Default for Trim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which side of a source line [`Trim`] cuts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrimMode {
+    /// Trim either side, keeping the [`Annotation`][crate::Annotation]s and their labels
+    /// centered
+    Center,
+    /// Only trim the right side, so indentation stays visible
+    ///
+    /// Lines are still shown up to the right-most [`Annotation`][crate::Annotation] and its
+    /// label.
+    KeepLeft,
+    /// Show whole lines, e.g. for logs that are processed further
+    Never,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) struct Margin {
+    /// The width of the whitespace in the left common to all lines.
+    leading_whitespace: usize,
     /// The available whitespace in the left that can be consumed when centering.
     whitespace_left: usize,
     /// The column of the beginning of left-most span.
@@ -21,6 +99,7 @@ pub(crate) struct Margin {
     /// The end column of a span label, including the span. Doesn't account for labels not in the
     /// same line as the span.
     label_right: usize,
+    trim: Trim,
 }
 
 impl Margin {
@@ -31,8 +110,9 @@ impl Margin {
         label_right: usize,
         term_width: usize,
         max_line_len: usize,
+        trim: Trim,
     ) -> Self {
-        // The 6 is padding to give a bit of room for `...` when displaying:
+        // The span padding gives a bit of room for `...` when displaying:
         // ```
         // error: message
         //   --> file.rs:16:58
@@ -42,26 +122,49 @@ impl Margin {
         // ```
 
         let mut m = Self {
-            whitespace_left: whitespace_left.saturating_sub(ELLIPSIS_PASSING),
-            span_left: span_left.saturating_sub(ELLIPSIS_PASSING),
-            span_right: span_right + ELLIPSIS_PASSING,
+            leading_whitespace: whitespace_left,
+            whitespace_left: whitespace_left.saturating_sub(trim.span_padding),
+            span_left: span_left.saturating_sub(trim.span_padding),
+            span_right: span_right + trim.span_padding,
             computed_left: 0,
             computed_right: 0,
             term_width,
-            label_right: label_right + ELLIPSIS_PASSING,
+            label_right: label_right + trim.span_padding,
+            trim,
         };
         m.compute(max_line_len);
         m
     }
 
+    /// Whether lines may be trimmed at all
+    pub(crate) fn trims(&self) -> bool {
+        self.trim.mode != TrimMode::Never
+    }
+
     pub(crate) fn was_cut_left(&self) -> bool {
         self.computed_left > 0
     }
 
     fn compute(&mut self, max_line_len: usize) {
-        // When there's a lot of whitespace (>20), we want to trim it as it is useless.
-        self.computed_left = if self.whitespace_left > LONG_WHITESPACE {
-            self.whitespace_left - (LONG_WHITESPACE - LONG_WHITESPACE_PADDING) // We want some padding.
+        match self.trim.mode {
+            TrimMode::Center => {}
+            TrimMode::KeepLeft => {
+                self.computed_left = 0;
+                self.computed_right = max(self.term_width, max(self.span_right, self.label_right));
+                return;
+            }
+            TrimMode::Never => {
+                self.computed_left = 0;
+                self.computed_right = max_line_len;
+                return;
+            }
+        }
+
+        // When there's a lot of whitespace, we want to trim it as it is useless.
+        self.computed_left = if self.leading_whitespace > self.trim.long_whitespace {
+            // We want some padding.
+            self.leading_whitespace
+                .saturating_sub(self.trim.long_whitespace_padding)
         } else {
             0
         };
@@ -103,7 +206,9 @@ impl Margin {
     }
 
     pub(crate) fn right(&self, line_len: usize) -> usize {
-        if line_len.saturating_sub(self.computed_left) <= self.term_width {
+        if self.trim.mode == TrimMode::Never
+            || line_len.saturating_sub(self.computed_left) <= self.term_width
+        {
             line_len
         } else {
             min(line_len, self.computed_right)
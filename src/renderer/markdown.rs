@@ -0,0 +1,236 @@
+//! Render a [`Report`] as Markdown, e.g. for pull request comments
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::{format, vec::Vec};
+use core::fmt::Write as _;
+
+use super::render::{origin_location, render};
+use super::stylesheet::Stylesheet;
+use super::{MarkdownSuggestion, Renderer};
+use crate::renderer::normalize_whitespace;
+use crate::renderer::source_map::SourceMap;
+use crate::snippet::Id;
+use crate::{Element, Group, Level, Patch, Report, Snippet, SuggestionStyle};
+
+pub(crate) fn render_markdown(renderer: &Renderer, groups: Report<'_>) -> String {
+    // Code blocks are shown verbatim, so they must not contain escape codes
+    let plain = Renderer {
+        stylesheet: Stylesheet::plain(),
+        hyperlink: false,
+        short_message: false,
+        ..renderer.clone()
+    };
+
+    let mut blocks: Vec<String> = Vec::new();
+    for group in groups {
+        if let Some(title) = &group.title {
+            let text = if title.allows_styling {
+                Cow::Borrowed(title.text.as_ref())
+            } else {
+                normalize_whitespace(&title.text)
+            };
+            let mut block = format!("**{}**", heading(&title.level, title.id.as_ref(), &text));
+            if let Some((_, code)) = group.inline_suggestion() {
+                block.push_str(&inline_code(code));
+            }
+            if renderer.applicability_marker
+                && let Some(marker) = group.applicability().and_then(|a| a.marker())
+            {
+                block.push(' ');
+                block.push_str(marker);
+            }
+            blocks.push(block);
+        }
+
+        let inline_suggestion = group.inline_suggestion().map(|(i, _)| i);
+        for (i, element) in group.elements.iter().enumerate() {
+            match element {
+                Element::Message(message) => {
+                    blocks.push(heading(&message.level, None, &message.text));
+                }
+                Element::Cause(cause) => {
+                    let art = render(
+                        &plain,
+                        &[Group::with_level(group.primary_level.clone()).element(cause.clone())],
+                    );
+                    blocks.push(code_block("text", &art));
+                }
+                Element::Suggestion(_) if inline_suggestion == Some(i) => {}
+                Element::Suggestion(suggestion) => {
+                    push_suggestion(renderer, &mut blocks, group, suggestion);
+                }
+                Element::MultiFileSuggestion(suggestion) => {
+                    for file in &suggestion.files {
+                        push_suggestion(renderer, &mut blocks, group, file);
+                    }
+                }
+                Element::Origin(origin) => {
+                    blocks.push(format!("`{}`", origin_location(&plain, origin)));
+                }
+                Element::Padding(_) => {}
+            }
+        }
+    }
+
+    let mut out = blocks.join("\n\n");
+    out.push('\n');
+    out
+}
+
+/// A code block per hunk, captioned with its location
+///
+/// In a [`Group`] whose suggestions differ in [`Applicability`][crate::Applicability], the
+/// caption is followed by the suggestion's marker.
+fn push_suggestion(
+    renderer: &Renderer,
+    blocks: &mut Vec<String>,
+    group: &Group<'_>,
+    suggestion: &Snippet<'_, Patch<'_>>,
+) {
+    if suggestion.suggestion_style == SuggestionStyle::Hidden {
+        return;
+    }
+    let sm = SourceMap::new(&suggestion.source, suggestion.line_start, renderer.width());
+    let marker = suggestion
+        .applicability
+        .marker()
+        .filter(|_| renderer.applicability_marker)
+        .filter(|_| group.applicability().is_none());
+    for hunk in sm.patched_hunks(&suggestion.markers) {
+        let mut caption = Vec::new();
+        if let Some(path) = &suggestion.path {
+            let path = normalize_whitespace(path);
+            let line = hunk.line;
+            caption.push(format!("`{path}:{line}`"));
+        }
+        caption.extend(marker.map(String::from));
+        if !caption.is_empty() {
+            blocks.push(caption.join(" "));
+        }
+        let block = match renderer.markdown_suggestion {
+            MarkdownSuggestion::Diff => code_block("diff", &diff(hunk.original, &hunk.patched)),
+            MarkdownSuggestion::GitHub => code_block("suggestion", &hunk.patched),
+        };
+        blocks.push(block);
+    }
+}
+
+/// `level[id]: text`, with `id` linked to its URL
+fn heading(level: &Level<'_>, id: Option<&Id<'_>>, text: &str) -> String {
+    let mut out = String::new();
+    let level_is_visible = level.name != Some(None) && !level.as_str().is_empty();
+    if level_is_visible {
+        out.push_str(&escape(&normalize_whitespace(level.as_str())));
+    }
+    if let Some(Id { id: Some(id), url }) = id {
+        let id = escape(&normalize_whitespace(id));
+        if level_is_visible {
+            out.push('[');
+        }
+        match url {
+            Some(url) => write!(&mut out, "[{id}](<{}>)", url.replace('>', "%3E")).unwrap(),
+            None => out.push_str(&id),
+        }
+        if level_is_visible {
+            out.push(']');
+        }
+    }
+    if !out.is_empty() {
+        out.push_str(": ");
+    }
+    // A trailing backslash is a hard line break in CommonMark
+    out.push_str(&escape(text).replace('\n', "\\\n"));
+    out
+}
+
+/// Escape Markdown syntax outside of code spans
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_code = false;
+    for c in text.chars() {
+        if c == '`' {
+            in_code = !in_code;
+        } else if !in_code && matches!(c, '\\' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// ``: `code` `` as a code span that is long enough to contain any backticks in `code`
+fn inline_code(code: &str) -> String {
+    let code = normalize_whitespace(code);
+    let fence = "`".repeat(longest_backtick_run(&code) + 1);
+    // Padding keeps backticks at either end from joining the fence
+    let padding = if code.starts_with('`') || code.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!(": {fence}{padding}{code}{padding}{fence}")
+}
+
+fn longest_backtick_run(content: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in content.chars() {
+        if c == '`' {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+/// A fenced code block that is long enough to contain any backticks in `content`
+fn code_block(info: &str, content: &str) -> String {
+    let fence = "`".repeat(longest_backtick_run(content).max(2) + 1);
+    let content = content.trim_end_matches('\n');
+    format!("{fence}{info}\n{content}\n{fence}")
+}
+
+/// Show `original` being replaced by `patched`, keeping common leading and
+/// trailing lines as context
+///
+/// The lines are kept verbatim, including `\r\n` line endings, so the diff can be applied.
+fn diff(original: &str, patched: &str) -> String {
+    let original = original.split_inclusive('\n').collect::<Vec<_>>();
+    let patched = patched.split_inclusive('\n').collect::<Vec<_>>();
+    let prefix = original
+        .iter()
+        .zip(&patched)
+        .take_while(|(o, p)| o == p)
+        .count();
+    let suffix = original[prefix..]
+        .iter()
+        .rev()
+        .zip(patched[prefix..].iter().rev())
+        .take_while(|(o, p)| o == p)
+        .count();
+
+    let mut out = String::new();
+    let mut push = |marker: char, line: &str| {
+        out.push(marker);
+        out.push_str(line);
+        if !line.ends_with('\n') {
+            out.push('\n');
+        }
+    };
+    for line in &original[..prefix] {
+        push(' ', line);
+    }
+    for line in &original[prefix..original.len() - suffix] {
+        push('-', line);
+    }
+    for line in &patched[prefix..patched.len() - suffix] {
+        push('+', line);
+    }
+    for line in &original[original.len() - suffix..] {
+        push(' ', line);
+    }
+    out
+}
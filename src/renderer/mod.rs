@@ -22,20 +22,30 @@ pub(crate) mod render;
 pub(crate) mod source_map;
 pub(crate) mod stylesheet;
 
+mod layout;
 mod margin;
+mod markdown;
 mod styled_buffer;
+mod svg;
+mod theme;
+mod unified_diff;
 
 use alloc::string::String;
+use alloc::vec::Vec;
 
-use crate::Report;
+use crate::{Patch, Report, Snippet};
 
 pub(crate) use render::ElementStyle;
 pub(crate) use render::UnderlineParts;
 pub(crate) use render::normalize_whitespace;
-pub(crate) use render::{LineAnnotation, LineAnnotationType, char_width, num_overlap};
+pub(crate) use render::{LineAnnotation, LineAnnotationType, Width, cluster_width, num_overlap};
 pub(crate) use stylesheet::Stylesheet;
 
 pub use anstyle::*;
+pub use layout::{GroupSize, SemanticStyle, Severity, StyledGroup, StyledRun};
+pub use margin::{Trim, TrimMode};
+pub use svg::{Palette, Svg};
+pub use theme::{ColorSupport, Theme};
 
 /// See [`Renderer::term_width`]
 pub const DEFAULT_TERM_WIDTH: usize = 140;
@@ -111,8 +121,19 @@ pub struct Renderer {
     term_width: usize,
     decor_style: DecorStyle,
     stylesheet: Stylesheet,
+    color_support: ColorSupport,
     hyperlink: bool,
     short_message: bool,
+    short_message_format: ShortMessageFormat,
+    markdown_suggestion: MarkdownSuggestion,
+    svg: Svg,
+    diff_context_lines: usize,
+    applicability_marker: bool,
+    elided_line_count: bool,
+    soft_wrap: bool,
+    trim: Trim,
+    ambiguous_width: AmbiguousWidth,
+    grapheme_clusters: bool,
 }
 
 impl Renderer {
@@ -124,8 +145,19 @@ impl Renderer {
             term_width: DEFAULT_TERM_WIDTH,
             decor_style: DecorStyle::Ascii,
             stylesheet: Stylesheet::plain(),
+            color_support: ColorSupport::TrueColor,
             hyperlink: false,
             short_message: false,
+            short_message_format: ShortMessageFormat::Rustc,
+            markdown_suggestion: MarkdownSuggestion::Diff,
+            svg: Svg::new(),
+            diff_context_lines: 3,
+            applicability_marker: false,
+            elided_line_count: false,
+            soft_wrap: false,
+            trim: Trim::new(),
+            ambiguous_width: AmbiguousWidth::Narrow,
+            grapheme_clusters: false,
         }
     }
 
@@ -140,38 +172,141 @@ impl Renderer {
     /// When testing styled terminal output, see the [`testing-colors` feature](crate#features)
     pub const fn styled() -> Self {
         Self {
-            stylesheet: Stylesheet {
-                error: DEFAULT_ERROR_STYLE,
-                warning: DEFAULT_WARNING_STYLE,
-                info: DEFAULT_INFO_STYLE,
-                note: DEFAULT_NOTE_STYLE,
-                help: DEFAULT_HELP_STYLE,
-                line_num: DEFAULT_LINE_NUM_STYLE,
-                emphasis: DEFAULT_EMPHASIS_STYLE,
-                none: DEFAULT_NONE_STYLE,
-                context: DEFAULT_CONTEXT_STYLE,
-                addition: DEFAULT_ADDITION_STYLE,
-                removal: DEFAULT_REMOVAL_STYLE,
-            },
+            stylesheet: Theme::DEFAULT.stylesheet,
             hyperlink: true,
             ..Self::plain()
         }
     }
 
     /// Abbreviate the message
+    ///
+    /// See [`Renderer::short_message_format`] for the layout of the abbreviated message.
+    /// Rendering panics when the primary [`Group`][crate::Group] has no
+    /// [`Title`][crate::Title], in every format.
     pub const fn short_message(mut self, short_message: bool) -> Self {
         self.short_message = short_message;
         self
     }
 
+    /// Set the layout used by [`Renderer::short_message`]
+    ///
+    /// The default is [`ShortMessageFormat::Rustc`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use annotate_snippets::*;
+    /// # use annotate_snippets::renderer::*;
+    /// let source = "fn main() { let x: u32 = \"\"; }";
+    /// let report = &[Level::ERROR
+    ///     .primary_title("mismatched types")
+    ///     .id("E0308")
+    ///     .element(
+    ///         Snippet::source(source)
+    ///             .path("src/main.rs")
+    ///             .annotation(AnnotationKind::Primary.span(25..27).label("expected `u32`")),
+    ///     )
+    ///     .element(Level::NOTE.message("expected type `u32`"))];
+    ///
+    /// let renderer = Renderer::plain()
+    ///     .short_message(true)
+    ///     .short_message_format(ShortMessageFormat::Msvc);
+    /// assert_eq!(
+    ///     renderer.render(report),
+    ///     "src/main.rs(1,26): error E0308: mismatched types: expected `u32`\n\
+    ///      src/main.rs(1,26): note: expected type `u32`"
+    /// );
+    /// ```
+    pub const fn short_message_format(mut self, format: ShortMessageFormat) -> Self {
+        self.short_message_format = format;
+        self
+    }
+
+    /// Mark uncertain suggestions with their [`Applicability`][crate::Applicability]
+    ///
+    /// When all of a [`Group`][crate::Group]'s suggestions share an applicability, it is shown on
+    /// the [`Title`][crate::Title], e.g. `help: consider borrowing here (maybe incorrect)`.
+    /// Otherwise each suggestion is marked where it starts.
+    /// [`Renderer::short_message`]s show the markers of all of the group's suggestions.
+    ///
+    /// The default is `false`.
+    pub const fn applicability_marker(mut self, yes: bool) -> Self {
+        self.applicability_marker = yes;
+        self
+    }
+
+    /// Show how many lines were folded out next to the `...` fold markers
+    ///
+    /// e.g. `... 37 lines omitted`
+    ///
+    /// The default is `false`.
+    ///
+    /// See also [`Snippet::max_lines`].
+    pub const fn elided_line_count(mut self, yes: bool) -> Self {
+        self.elided_line_count = yes;
+        self
+    }
+
     /// Set the width to render within
     ///
-    /// Affects the rendering of [`Snippet`][crate::Snippet]s
+    /// Affects the rendering of [`Snippet`]s
     pub const fn term_width(mut self, term_width: usize) -> Self {
         self.term_width = term_width;
         self
     }
 
+    /// Set how source lines wider than the [`term_width`][Self::term_width] are trimmed
+    ///
+    /// The default is [`Trim::new`].
+    pub const fn trim(mut self, trim: Trim) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Wrap source lines longer than the [`term_width`][Self::term_width] over several rows,
+    /// instead of trimming them around their [`Annotation`][crate::Annotation]s
+    ///
+    /// Rows continuing a line are marked in the gutter.
+    ///
+    /// The default is `false`.
+    pub const fn soft_wrap(mut self, yes: bool) -> Self {
+        self.soft_wrap = yes;
+        self
+    }
+
+    /// Set how wide East Asian ambiguous width characters are, e.g. `①`, `■` and Greek and
+    /// Cyrillic letters
+    ///
+    /// Terminals in CJK locales usually render them [wide][AmbiguousWidth::Wide], which
+    /// underlines and trimming must match to line up. The renderer's own decor, like `…`, is
+    /// always measured narrow.
+    ///
+    /// The default is [`AmbiguousWidth::Narrow`].
+    pub const fn ambiguous_width(mut self, ambiguous_width: AmbiguousWidth) -> Self {
+        self.ambiguous_width = ambiguous_width;
+        self
+    }
+
+    /// Measure, underline and trim source code by grapheme cluster, e.g. emoji joined with
+    /// ZWJ, flags and letters with combining accents
+    ///
+    /// Annotations pointing into the middle of a cluster cover all of it. Otherwise each `char`
+    /// is measured on its own and ZWJ is removed from the output, like rustc.
+    ///
+    /// The default is `false`.
+    pub const fn grapheme_clusters(mut self, yes: bool) -> Self {
+        self.grapheme_clusters = yes;
+        self
+    }
+
+    /// How source code is split into columns
+    pub(crate) const fn width(&self) -> Width {
+        Width {
+            ambiguous: self.ambiguous_width,
+            grapheme_clusters: self.grapheme_clusters,
+        }
+    }
+
     /// Set the character set used for rendering decor
     pub const fn decor_style(mut self, decor_style: DecorStyle) -> Self {
         self.decor_style = decor_style;
@@ -230,10 +365,207 @@ impl Renderer {
     pub fn render(&self, groups: Report<'_>) -> String {
         render::render(self, groups)
     }
+
+    /// Lay out a diagnostic [`Report`] as lines of styled text, e.g. for a TUI or GUI
+    ///
+    /// This is what [`Renderer::render`] shows, one [`StyledGroup`] per
+    /// [`Group`][crate::Group], with the [`SemanticStyle`] of each run of text rather than
+    /// ANSI escape codes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use annotate_snippets::*;
+    /// # use annotate_snippets::renderer::*;
+    /// let report = &[Group::with_title(Level::ERROR.primary_title("unresolved import"))];
+    ///
+    /// let groups = Renderer::plain().render_styled(report);
+    /// assert_eq!(groups[0].severity, Severity::Error);
+    /// let title = &groups[0].lines[0];
+    /// assert_eq!(title[0].text, "error");
+    /// assert_eq!(title[0].style, SemanticStyle::Level(Severity::Error));
+    /// ```
+    pub fn render_styled(&self, groups: Report<'_>) -> Vec<StyledGroup> {
+        layout::render_styled(self, groups)
+    }
+
+    /// Measure how many rows and columns each [`Group`][crate::Group] of a diagnostic
+    /// [`Report`] takes, e.g. for pagination
+    ///
+    /// This lays out the report like [`Renderer::render`] without building the output.
+    /// The rendered groups are joined by newlines, so the whole report takes the sum of their
+    /// rows.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use annotate_snippets::*;
+    /// # use annotate_snippets::renderer::*;
+    /// let report = &[Group::with_title(Level::ERROR.primary_title("unresolved import"))];
+    ///
+    /// let sizes = Renderer::plain().measure(report);
+    /// assert_eq!((sizes[0].rows, sizes[0].columns), (1, 24));
+    /// ```
+    pub fn measure(&self, groups: Report<'_>) -> Vec<GroupSize> {
+        layout::measure(self, groups)
+    }
+
+    /// Render a diagnostic [`Report`] as Markdown, e.g. for pull request comments
+    ///
+    /// - [`Title`][crate::Title]s are bold, with their [`id`][crate::Title::id] linked to their
+    ///   [`id_url`][crate::Title::id_url]
+    /// - [`Snippet`]s with [`Annotation`][crate::Annotation]s are fenced code
+    ///   blocks of the rendered source, without styling
+    /// - [`Patch`]es are fenced code blocks per
+    ///   [`Renderer::markdown_suggestion`]
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use annotate_snippets::*;
+    /// # use annotate_snippets::renderer::*;
+    /// let source = "let x = 1;";
+    /// let report = &[
+    ///     Level::WARNING.primary_title("unused variable: `x`").element(
+    ///         Snippet::source(source)
+    ///             .path("src/lib.rs")
+    ///             .annotation(AnnotationKind::Primary.span(4..5)),
+    ///     ),
+    ///     Level::HELP
+    ///         .secondary_title("if this is intentional, prefix it with an underscore")
+    ///         .element(
+    ///             Snippet::source(source)
+    ///                 .path("src/lib.rs")
+    ///                 .patch(Patch::new(4..5, "_x")),
+    ///         ),
+    /// ];
+    ///
+    /// let markdown = Renderer::plain().render_markdown(report);
+    /// assert!(markdown.contains("```diff\n-let x = 1;\n+let _x = 1;\n```"));
+    /// ```
+    pub fn render_markdown(&self, groups: Report<'_>) -> String {
+        markdown::render_markdown(self, groups)
+    }
+
+    /// Set how [`Renderer::render_markdown`] shows [`Patch`]es
+    ///
+    /// The default is [`MarkdownSuggestion::Diff`].
+    pub const fn markdown_suggestion(mut self, markdown_suggestion: MarkdownSuggestion) -> Self {
+        self.markdown_suggestion = markdown_suggestion;
+        self
+    }
+
+    /// Render a diagnostic [`Report`] as an SVG image of a terminal showing it
+    ///
+    /// The styles set on the [`Renderer`] are used, so this is most useful with
+    /// [`Renderer::styled`].
+    /// See [`Renderer::svg`] for how the terminal looks.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use annotate_snippets::*;
+    /// # use annotate_snippets::renderer::*;
+    /// let report = &[Group::with_title(
+    ///     Level::ERROR
+    ///         .primary_title("unresolved import `baz::zed`")
+    ///         .id("E0432"),
+    /// )];
+    ///
+    /// let renderer = Renderer::styled().svg(Svg::new().palette(Palette::WIN10_CONSOLE));
+    /// let svg = renderer.render_svg(report);
+    /// assert!(svg.starts_with("<svg "));
+    /// ```
+    pub fn render_svg(&self, groups: Report<'_>) -> String {
+        svg::render_svg(self, groups)
+    }
+
+    /// Set the font, colors, and background of [`Renderer::render_svg`]
+    pub const fn svg(mut self, svg: Svg) -> Self {
+        self.svg = svg;
+        self
+    }
+
+    /// Render the [`Patch`]es of a [`Snippet`] as a unified diff
+    ///
+    /// The diff is against the [`Snippet::path`], so it can be fed to `git apply` or
+    /// `patch -p1`.
+    /// Without a path, the header names the file `<anonymous>`.
+    /// Line numbers start at [`Snippet::line_start`].
+    /// A set [`Snippet::applicability`] is shown in an `Applicability: MaybeIncorrect` line
+    /// before the file header, which `git apply` and `patch` skip.
+    /// The diff is empty when the patches change nothing.
+    ///
+    /// See [`Renderer::diff_context_lines`] for how many unchanged lines are shown.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use annotate_snippets::*;
+    /// let source = "fn main() {\n    let x = 1;\n}\n";
+    /// let snippet = Snippet::source(source)
+    ///     .path("src/main.rs")
+    ///     .patch(Patch::new(20..21, "_x"));
+    ///
+    /// let diff = Renderer::plain().render_unified_diff(&snippet);
+    /// assert_eq!(
+    ///     diff,
+    ///     "\
+    /// --- a/src/main.rs
+    /// +++ b/src/main.rs
+    /// @@ -1,3 +1,3 @@
+    ///  fn main() {
+    /// -    let x = 1;
+    /// +    let _x = 1;
+    ///  }
+    /// "
+    /// );
+    /// ```
+    pub fn render_unified_diff(&self, snippet: &Snippet<'_, Patch<'_>>) -> String {
+        unified_diff::render_unified_diff(self, snippet)
+    }
+
+    /// Set the number of unchanged lines [`Renderer::render_unified_diff`] shows around
+    /// each change
+    ///
+    /// The default is `3`, like `diff -u`.
+    /// `git apply` needs `--unidiff-zero` for diffs without context.
+    pub const fn diff_context_lines(mut self, lines: usize) -> Self {
+        self.diff_context_lines = lines;
+        self
+    }
 }
 
 /// Customize [`Renderer::styled`]
 impl Renderer {
+    /// Replace the output style for everything with a preset
+    ///
+    /// ```rust
+    /// # use annotate_snippets::renderer::{Renderer, Theme};
+    /// let renderer = Renderer::styled().theme(Theme::LIGHT);
+    /// ```
+    pub const fn theme(mut self, theme: Theme) -> Self {
+        self.stylesheet = theme.stylesheet;
+        self
+    }
+
+    /// Declare the colors the terminal can show
+    ///
+    /// Colors of the output styles that it lacks are replaced with the closest ones it has, with
+    /// [`ColorSupport::Monochrome`] keeping only [bold][Effects::BOLD] and
+    /// [underlined][Effects::UNDERLINE] text.
+    ///
+    /// ```rust
+    /// # use annotate_snippets::renderer::{ColorSupport, RgbColor, Renderer, Style};
+    /// let renderer = Renderer::styled()
+    ///     .error(RgbColor(255, 95, 0).on_default())
+    ///     .color_support(ColorSupport::Ansi16);
+    /// ```
+    pub const fn color_support(mut self, color_support: ColorSupport) -> Self {
+        self.color_support = color_support;
+        self
+    }
+
     /// Override the output style for [error][crate::Level::ERROR]
     pub const fn error(mut self, style: Style) -> Self {
         self.stylesheet.error = style;
@@ -264,7 +596,7 @@ impl Renderer {
         self
     }
 
-    /// Override the output style for line numbers in the [`Snippet`][crate::Snippet] gutter
+    /// Override the output style for line numbers in the [`Snippet`] gutter
     pub const fn line_num(mut self, style: Style) -> Self {
         self.stylesheet.line_num = style;
         self
@@ -283,13 +615,13 @@ impl Renderer {
         self
     }
 
-    /// Override the output style for [`Patch`][crate::Patch] additions
+    /// Override the output style for [`Patch`] additions
     pub const fn addition(mut self, style: Style) -> Self {
         self.stylesheet.addition = style;
         self
     }
 
-    /// Override the output style for [`Patch`][crate::Patch] removals
+    /// Override the output style for [`Patch`] removals
     pub const fn removal(mut self, style: Style) -> Self {
         self.stylesheet.removal = style;
         self
@@ -308,6 +640,43 @@ impl Renderer {
     }
 }
 
+/// The layout of a [`Renderer::short_message`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortMessageFormat {
+    /// `path:line:col: error[E0308]: title: labels`, like `rustc --error-format=short`
+    ///
+    /// Only the primary [`Group`][crate::Group]'s [`Title`][crate::Title] is shown.
+    Rustc,
+    /// `path:line:col: error: title: labels [E0308]`, like gcc and clang
+    ///
+    /// Understood by Vim's quickfix list and Emacs' `compilation-mode`.
+    /// Each [`Group`][crate::Group] and [`Message`][crate::Message] gets its own line.
+    Gcc,
+    /// `path(line,col): error E0308: title: labels`, like MSVC
+    ///
+    /// Understood by Visual Studio's error list.
+    /// Each [`Group`][crate::Group] and [`Message`][crate::Message] gets its own line.
+    Msvc,
+}
+
+/// The kind of code block [`Renderer::render_markdown`] uses for [`Patch`]es
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkdownSuggestion {
+    /// A `diff` block of the removed and added lines
+    Diff,
+    /// A GitHub `suggestion` block with the patched lines, which reviewers can commit directly
+    GitHub,
+}
+
+/// How wide East Asian ambiguous width characters are, see [`Renderer::ambiguous_width`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmbiguousWidth {
+    /// One column, like most terminals outside of CJK locales
+    Narrow,
+    /// Two columns, like terminals in CJK locales
+    Wide,
+}
+
 /// The character set for rendering for decor
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DecorStyle {
@@ -361,6 +730,21 @@ impl DecorStyle {
         }
     }
 
+    fn wrap_marker(&self) -> &'static str {
+        match self {
+            Self::Ascii => ">",
+            Self::Unicode => "↪",
+        }
+    }
+
+    /// The two halves of the mark between two columns for an insertion point
+    fn insertion_point(&self) -> [char; 2] {
+        match self {
+            Self::Ascii => ['/', '\\'],
+            Self::Unicode => ['╱', '╲'],
+        }
+    }
+
     fn margin(&self) -> &'static str {
         match self {
             Self::Ascii => "...",
@@ -5,39 +5,65 @@ use alloc::borrow::ToOwned;
 use alloc::collections::BTreeMap;
 use alloc::string::{String, ToString};
 use alloc::{format, vec, vec::Vec};
-use core::cmp::{Ordering, Reverse, max, min};
+use core::cmp::{Reverse, max, min};
 use core::fmt;
+use core::ops::RangeInclusive;
 
 use anstyle::Style;
+use unicode_segmentation::UnicodeSegmentation as _;
 
+use super::AmbiguousWidth;
 use super::DecorStyle;
 use super::Renderer;
+use super::ShortMessageFormat;
 use super::margin::Margin;
 use super::stylesheet::Stylesheet;
+use crate::gutter::LineLabels;
 use crate::level::{Level, LevelInner};
 use crate::renderer::source_map::{
-    AnnotatedLineInfo, LineInfo, Loc, SourceMap, SplicedLines, SubstitutionHighlight, TrimmedPatch,
+    AnnotatedLineInfo, EOF_MARKER, LineInfo, Loc, SourceMap, SplicedLines, SubstitutionHighlight,
+    TrimmedPatch,
 };
 use crate::renderer::styled_buffer::StyledBuffer;
 use crate::snippet::Id;
 use crate::{
     Annotation, AnnotationKind, Element, Group, Message, Origin, Padding, Patch, Report, Snippet,
-    Title,
+    SuggestionStyle, Title,
 };
 
 const ANONYMIZED_LINE_NUM: &str = "LL";
 
 pub(crate) fn render(renderer: &Renderer, groups: Report<'_>) -> String {
+    let stylesheet = renderer.stylesheet.downgrade(renderer.color_support);
+    let mut out_string = String::new();
+    for (i, (level, buffer)) in render_buffers(renderer, groups).iter().enumerate() {
+        if i != 0 {
+            out_string.push('\n');
+        }
+        buffer.render(level, &stylesheet, &mut out_string).unwrap();
+    }
+    out_string
+}
+
+/// Lay out each [`Group`] into a [`StyledBuffer`], along with the [`Level`]
+/// its styles are resolved against
+///
+/// The buffers are joined by newlines when shown.
+pub(crate) fn render_buffers<'a>(
+    renderer: &Renderer,
+    groups: Report<'a>,
+) -> Vec<(Level<'a>, StyledBuffer)> {
     if renderer.short_message {
-        render_short_message(renderer, groups).unwrap()
+        render_short_message(renderer, groups).into_iter().collect()
     } else {
-        let (max_line_num, report_primary_path, groups) = pre_process(groups);
+        let (max_line_num, max_label_len, report_primary_path, groups) =
+            pre_process(renderer, groups);
         let max_line_num_len = if renderer.anonymized_snippet_line_numbers {
             ANONYMIZED_LINE_NUM.len()
         } else {
-            num_decimal_digits(max_line_num)
+            max(num_decimal_digits(max_line_num), max_label_len)
         };
-        let mut out_string = String::new();
+        let mut buffers = Vec::with_capacity(groups.len());
         let group_len = groups.len();
         for (
             g,
@@ -69,6 +95,20 @@ pub(crate) fn render(renderer: &Renderer, groups: Report<'_>) -> String {
                     matches!(peek, Some(PreProcessedElement::Message(_))),
                     buffer_msg_line_offset,
                 );
+                if let Some((_, code)) = group.inline_suggestion() {
+                    let line = buffer.num_lines() - 1;
+                    buffer.append(
+                        line,
+                        &format!(": `{}`", normalize_whitespace(code)),
+                        ElementStyle::NoStyle,
+                    );
+                }
+                if renderer.applicability_marker
+                    && let Some(marker) = group.applicability().and_then(|a| a.marker())
+                {
+                    let line = buffer.num_lines() - 1;
+                    buffer.append(line, &format!(" {marker}"), ElementStyle::NoStyle);
+                }
                 let buffer_msg_line_offset = buffer.num_lines();
 
                 if matches!(peek, Some(PreProcessedElement::Message(_))) {
@@ -111,7 +151,7 @@ pub(crate) fn render(renderer: &Renderer, groups: Report<'_>) -> String {
                             buffer_msg_line_offset,
                         );
                     }
-                    PreProcessedElement::Cause((cause, source_map, annotated_lines)) => {
+                    PreProcessedElement::Cause((cause, source_map, annotated_lines, omitted)) => {
                         let is_primary = primary_path == cause.path.as_ref() && !seen_primary;
                         seen_primary |= is_primary;
                         render_snippet_annotations(
@@ -122,6 +162,7 @@ pub(crate) fn render(renderer: &Renderer, groups: Report<'_>) -> String {
                             is_primary,
                             &source_map,
                             &annotated_lines,
+                            omitted,
                             max_depth,
                             peek.is_some() || (g == 0 && group_len > 1),
                             is_first,
@@ -153,9 +194,28 @@ pub(crate) fn render(renderer: &Renderer, groups: Report<'_>) -> String {
                         source_map,
                         spliced_lines,
                         display_suggestion,
+                        file,
                     )) => {
-                        let matches_previous_suggestion =
-                            last_suggestion_path == Some(suggestion.path.as_ref());
+                        let matches_previous_suggestion = file != SuggestionFile::First
+                            && last_suggestion_path == Some(suggestion.path.as_ref());
+                        // Suggestions that differ from the rest of the group are marked on
+                        // their own, on the row before their code
+                        let marker = suggestion
+                            .applicability
+                            .marker()
+                            .filter(|_| renderer.applicability_marker)
+                            .filter(|_| group.applicability().is_none());
+                        if marker.is_some() && matches_previous_suggestion {
+                            let line = buffer.num_lines();
+                            draw_col_separator_no_space(
+                                renderer,
+                                &mut buffer,
+                                line,
+                                max_line_num_len + 1,
+                            );
+                        }
+                        let marker_line =
+                            buffer.num_lines() - usize::from(matches_previous_suggestion);
                         emit_suggestion_default(
                             renderer,
                             &mut buffer,
@@ -166,10 +226,18 @@ pub(crate) fn render(renderer: &Renderer, groups: Report<'_>) -> String {
                             &source_map,
                             primary_path.or(report_primary_path),
                             matches_previous_suggestion,
+                            file,
                             is_first,
                             //matches!(peek, Some(Element::Message(_) | Element::Padding(_))),
                             peek.is_some(),
                         );
+                        if let Some(marker) = marker {
+                            buffer.append(
+                                marker_line,
+                                &format!(" {marker}"),
+                                ElementStyle::NoStyle,
+                            );
+                        }
 
                         if matches!(peek, Some(PreProcessedElement::Suggestion(_))) {
                             last_suggestion_path = Some(suggestion.path.as_ref());
@@ -222,75 +290,32 @@ pub(crate) fn render(renderer: &Renderer, groups: Report<'_>) -> String {
                     }
                 }
             }
-            buffer
-                .render(&level, &renderer.stylesheet, &mut out_string)
-                .unwrap();
-            if g != group_len - 1 {
-                out_string.push('\n');
-            }
+            buffers.push((level, buffer));
         }
-        out_string
+        buffers
     }
 }
 
-fn render_short_message(renderer: &Renderer, groups: &[Group<'_>]) -> Result<String, fmt::Error> {
-    let mut buffer = StyledBuffer::new();
-    let mut labels = None;
+fn render_short_message<'a>(
+    renderer: &Renderer,
+    groups: Report<'a>,
+) -> Option<(Level<'a>, StyledBuffer)> {
     let group = groups.first().expect("Expected at least one group");
 
     let Some(title) = &group.title else {
         panic!("Expected a Title");
     };
 
-    if let Some(Element::Cause(cause)) = group
-        .elements
-        .iter()
-        .find(|e| matches!(e, Element::Cause(_)))
-    {
-        let labels_inner = cause
-            .markers
-            .iter()
-            .filter_map(|ann| match &ann.label {
-                Some(msg) if ann.kind.is_primary() => {
-                    if !msg.trim().is_empty() {
-                        Some(msg.to_string())
-                    } else {
-                        None
-                    }
-                }
-                _ => None,
-            })
-            .collect::<Vec<_>>()
-            .join(", ");
-        if !labels_inner.is_empty() {
-            labels = Some(labels_inner);
-        }
-
-        if let Some(path) = &cause.path {
-            let mut origin = Origin::path(path.as_ref());
-
-            let source_map = SourceMap::new(&cause.source, cause.line_start);
-            let (_depth, annotated_lines) =
-                source_map.annotated_lines(cause.markers.clone(), cause.fold);
+    if renderer.short_message_format != ShortMessageFormat::Rustc {
+        return render_short_message_lines(renderer, groups);
+    }
 
-            if let Some(primary_line) = annotated_lines
-                .iter()
-                .find(|l| l.annotations.iter().any(LineAnnotation::is_primary))
-                .or(annotated_lines.iter().find(|l| !l.annotations.is_empty()))
-            {
-                origin.line = Some(primary_line.line_index);
-                if let Some(first_annotation) = primary_line
-                    .annotations
-                    .iter()
-                    .min_by_key(|a| (Reverse(a.is_primary()), a.start.char))
-                {
-                    origin.char_column = Some(first_annotation.start.char + 1);
-                }
-            }
+    let mut buffer = StyledBuffer::new();
 
-            render_origin(renderer, &mut buffer, 0, &origin, true, true, true, 0);
-            buffer.append(0, ": ", ElementStyle::LineAndColumn);
-        }
+    let (origin, labels) = short_message_summary(renderer, group);
+    if let Some(origin) = origin {
+        render_origin(renderer, &mut buffer, 0, &origin, true, true, true, 0);
+        buffer.append(0, ": ", ElementStyle::LineAndColumn);
     }
 
     render_title(
@@ -306,11 +331,199 @@ fn render_short_message(renderer: &Renderer, groups: &[Group<'_>]) -> Result<Str
     if let Some(labels) = labels {
         buffer.append(0, &format!(": {labels}"), ElementStyle::NoStyle);
     }
+    if renderer.applicability_marker
+        && let Some(markers) = group.applicability_markers()
+    {
+        buffer.append(0, &format!(" {markers}"), ElementStyle::NoStyle);
+    }
 
-    let mut out_string = String::new();
-    buffer.render(&title.level, &renderer.stylesheet, &mut out_string)?;
+    Some((title.level.clone(), buffer))
+}
 
-    Ok(out_string)
+/// Render each [`Title`] and [`Message`] on its own line, for tools that parse diagnostics
+/// line-by-line
+fn render_short_message_lines<'a>(
+    renderer: &Renderer,
+    groups: Report<'a>,
+) -> Option<(Level<'a>, StyledBuffer)> {
+    let mut buffer = StyledBuffer::new();
+    let mut primary_origin = None;
+    for (g, group) in groups.iter().enumerate() {
+        let (origin, labels) = short_message_summary(renderer, group);
+        let origin = origin.or_else(|| {
+            group.elements.iter().find_map(|e| match e {
+                Element::Origin(origin) => Some(origin.clone()),
+                _ => None,
+            })
+        });
+        if g == 0 {
+            primary_origin.clone_from(&origin);
+        }
+        // Secondary groups without a location of their own are reported at
+        // the location of the primary group, so editors can still jump to them
+        let origin = origin.or_else(|| primary_origin.clone());
+
+        if let Some(title) = &group.title {
+            let line = buffer.num_lines();
+            render_short_message_line(
+                renderer,
+                &mut buffer,
+                line,
+                origin.as_ref(),
+                title,
+                labels.as_deref(),
+                group
+                    .applicability_markers()
+                    .filter(|_| renderer.applicability_marker)
+                    .as_deref(),
+            );
+        }
+        for element in &group.elements {
+            if let Element::Message(message) = element {
+                let line = buffer.num_lines();
+                render_short_message_line(
+                    renderer,
+                    &mut buffer,
+                    line,
+                    origin.as_ref(),
+                    message,
+                    None,
+                    None,
+                );
+            }
+        }
+    }
+
+    let group = groups.first()?;
+    Some((group.primary_level.clone(), buffer))
+}
+
+fn render_short_message_line(
+    renderer: &Renderer,
+    buffer: &mut StyledBuffer,
+    line: usize,
+    origin: Option<&Origin<'_>>,
+    title: &dyn MessageOrTitle,
+    labels: Option<&str>,
+    markers: Option<&str>,
+) {
+    let is_msvc = renderer.short_message_format == ShortMessageFormat::Msvc;
+    if let Some(origin) = origin {
+        let location = format_origin_location(renderer, origin, renderer.short_message_format);
+        buffer.append(line, &location, ElementStyle::LineAndColumn);
+        buffer.append(line, ": ", ElementStyle::LineAndColumn);
+    }
+
+    let level = title.level();
+    let label_style = ElementStyle::Level(level.level);
+    let level_is_visible = level.name != Some(None) && !level.as_str().is_empty();
+    let id = title.id().and_then(|id| {
+        id.id.as_deref().map(|id_text| {
+            let url = id
+                .url
+                .as_deref()
+                .filter(|_| renderer.hyperlink)
+                .map(Hyperlink::with_url)
+                .unwrap_or_default();
+            format!("{url}{id_text}{url:#}")
+        })
+    });
+
+    if level_is_visible {
+        buffer.append(line, level.as_str(), label_style);
+    }
+    if let Some(id) = id.as_deref().filter(|_| is_msvc) {
+        if level_is_visible {
+            buffer.append(line, " ", label_style);
+        }
+        buffer.append(line, id, label_style);
+    }
+    if level_is_visible || (is_msvc && id.is_some()) {
+        buffer.append(line, ": ", ElementStyle::NoStyle);
+    }
+
+    let text = if title.allows_styling() {
+        Cow::Borrowed(title.text())
+    } else {
+        normalize_whitespace(title.text())
+    };
+    // Each diagnostic must stay on a single line
+    let text = text.lines().collect::<Vec<_>>().join(" ");
+    buffer.append(line, &text, ElementStyle::NoStyle);
+
+    if let Some(labels) = labels {
+        buffer.append(line, &format!(": {labels}"), ElementStyle::NoStyle);
+    }
+    if let Some(markers) = markers {
+        buffer.append(line, &format!(" {markers}"), ElementStyle::NoStyle);
+    }
+    if let Some(id) = id.as_deref().filter(|_| !is_msvc) {
+        buffer.append(line, " [", label_style);
+        buffer.append(line, id, label_style);
+        buffer.append(line, "]", label_style);
+    }
+}
+
+/// The location and primary labels summarizing a [`Group`] for [`Renderer::short_message`]
+fn short_message_summary<'a>(
+    renderer: &Renderer,
+    group: &'a Group<'a>,
+) -> (Option<Origin<'a>>, Option<String>) {
+    let Some(Element::Cause(cause)) = group
+        .elements
+        .iter()
+        .find(|e| matches!(e, Element::Cause(_)))
+    else {
+        return (None, None);
+    };
+
+    let labels = cause
+        .markers
+        .iter()
+        .filter_map(|ann| match &ann.label {
+            Some(msg) if ann.kind.is_primary() => {
+                if !msg.trim().is_empty() {
+                    Some(msg.to_string())
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let labels = if labels.is_empty() {
+        None
+    } else {
+        Some(labels)
+    };
+
+    let origin = cause.path.as_ref().map(|path| {
+        let mut origin = Origin::path(path.as_ref());
+
+        let source_map = cause_source_map(renderer, cause);
+        let (_depth, annotated_lines) = source_map.annotated_lines(cause);
+
+        // Rows of a hexdump start with their offset instead
+        if !cause.is_hexdump()
+            && let Some(primary_line) = annotated_lines
+                .iter()
+                .find(|l| l.annotations.iter().any(LineAnnotation::is_primary))
+                .or(annotated_lines.iter().find(|l| !l.annotations.is_empty()))
+        {
+            origin.line = Some(primary_line.line_index);
+            if let Some(first_annotation) = primary_line
+                .annotations
+                .iter()
+                .min_by_key(|a| (Reverse(a.is_primary()), a.start.char))
+            {
+                origin.char_column = Some(first_annotation.start.char + 1);
+            }
+        }
+        origin
+    });
+
+    (origin, labels)
 }
 
 #[allow(clippy::too_many_arguments, reason = "All arguments are necessary")]
@@ -357,7 +570,7 @@ fn render_title(
     if level_is_visible || title.id().is_some() {
         if level_is_visible {
             buffer.append(buffer_msg_line_offset, title.level().as_str(), label_style);
-            label_width += str_width(title.level().as_str());
+            label_width += str_width(title.level().as_str(), renderer.width());
         }
 
         if let Some(Id { id: Some(id), url }) = &title.id() {
@@ -375,7 +588,7 @@ fn render_title(
             buffer.append(buffer_msg_line_offset, &format!("{url}"), label_style);
             buffer.append(buffer_msg_line_offset, id, label_style);
             buffer.append(buffer_msg_line_offset, &format!("{url:#}"), label_style);
-            label_width += str_width(id);
+            label_width += str_width(id, renderer.width());
 
             if level_is_visible {
                 buffer.append(buffer_msg_line_offset, "]", label_style);
@@ -496,24 +709,41 @@ fn render_origin(
         );
     }
 
-    let str = {
-        use core::fmt::Write as _;
+    let str = origin_location(renderer, origin);
+    buffer.append(buffer_msg_line_offset, &str, ElementStyle::LineAndColumn);
+}
 
-        let mut buffer = origin.path.as_ref().to_owned();
-        if let Some(line) = origin.line {
-            if renderer.anonymized_origin_line_numbers {
-                let line = ANONYMIZED_LINE_NUM;
-                write!(&mut buffer, ":{line}").unwrap();
-            } else {
-                write!(&mut buffer, ":{line}").unwrap();
-            }
-            if let Some(col) = origin.char_column {
-                write!(&mut buffer, ":{col}").unwrap();
-            }
-        }
-        buffer
+/// `path:line:col`
+pub(crate) fn origin_location(renderer: &Renderer, origin: &Origin<'_>) -> String {
+    format_origin_location(renderer, origin, ShortMessageFormat::Rustc)
+}
+
+/// [`origin_location`], or `path(line,col)` for [`ShortMessageFormat::Msvc`]
+fn format_origin_location(
+    renderer: &Renderer,
+    origin: &Origin<'_>,
+    format: ShortMessageFormat,
+) -> String {
+    use core::fmt::Write as _;
+
+    let (open, separator, close) = match format {
+        ShortMessageFormat::Msvc => ("(", ",", ")"),
+        ShortMessageFormat::Rustc | ShortMessageFormat::Gcc => (":", ":", ""),
     };
-    buffer.append(buffer_msg_line_offset, &str, ElementStyle::LineAndColumn);
+    let mut buffer = origin.path.as_ref().to_owned();
+    if let Some(line) = origin.line {
+        if renderer.anonymized_origin_line_numbers {
+            let line = ANONYMIZED_LINE_NUM;
+            write!(&mut buffer, "{open}{line}").unwrap();
+        } else {
+            write!(&mut buffer, "{open}{line}").unwrap();
+        }
+        if let Some(col) = origin.char_column {
+            write!(&mut buffer, "{separator}{col}").unwrap();
+        }
+        buffer.push_str(close);
+    }
+    buffer
 }
 
 #[allow(clippy::too_many_arguments, reason = "All arguments are necessary")]
@@ -525,6 +755,7 @@ fn render_snippet_annotations(
     is_primary: bool,
     sm: &SourceMap<'_>,
     annotated_lines: &[AnnotatedLineInfo<'_>],
+    omitted: usize,
     multiline_depth: usize,
     is_cont: bool,
     is_first: bool,
@@ -536,10 +767,11 @@ fn render_snippet_annotations(
         //let is_primary = primary_path == Some(&origin.path);
 
         if is_primary {
-            if let Some(primary_line) = annotated_lines
-                .iter()
-                .find(|l| l.annotations.iter().any(LineAnnotation::is_primary))
-                .or(annotated_lines.iter().find(|l| !l.annotations.is_empty()))
+            if !snippet.is_hexdump()
+                && let Some(primary_line) = annotated_lines
+                    .iter()
+                    .find(|l| l.annotations.iter().any(LineAnnotation::is_primary))
+                    .or(annotated_lines.iter().find(|l| !l.annotations.is_empty()))
             {
                 origin.line = Some(primary_line.line_index);
                 if let Some(first_annotation) = primary_line
@@ -568,10 +800,11 @@ fn render_snippet_annotations(
                 buffer_msg_line_offset,
                 max_line_num_len + 1,
             );
-            if let Some(first_line) = annotated_lines
-                .iter()
-                .find(|l| !l.annotations.is_empty())
-                .or(annotated_lines.first())
+            if !snippet.is_hexdump()
+                && let Some(first_line) = annotated_lines
+                    .iter()
+                    .find(|l| !l.annotations.is_empty())
+                    .or(annotated_lines.first())
             {
                 origin.line = Some(first_line.line_index);
                 if let Some(first_annotation) = first_line.annotations.first() {
@@ -645,6 +878,23 @@ fn render_snippet_annotations(
     // Contains the vertical lines' positions for active multiline annotations
     let mut multilines = Vec::new();
 
+    let width_offset = 3 + max_line_num_len;
+    let code_offset = if multiline_depth == 0 {
+        width_offset
+    } else {
+        width_offset + multiline_depth + 1
+    };
+
+    let column_width = renderer.term_width.saturating_sub(code_offset);
+
+    let wrapped;
+    let annotated_lines = if renderer.soft_wrap {
+        wrapped = wrap_annotated_lines(renderer, sm, annotated_lines, column_width);
+        &wrapped
+    } else {
+        annotated_lines
+    };
+
     // Get the left-side margin to remove it
     let mut whitespace_margin = usize::MAX;
     for line_info in annotated_lines {
@@ -685,24 +935,18 @@ fn render_snippet_annotations(
     let mut label_right_margin = 0;
     let mut max_line_len = 0;
     for line_info in annotated_lines {
-        max_line_len = max(max_line_len, str_width(line_info.line));
+        max_line_len = max(max_line_len, str_width(line_info.line, renderer.width()));
         for ann in &line_info.annotations {
             span_right_margin = max(span_right_margin, ann.start.display);
             span_right_margin = max(span_right_margin, ann.end.display);
             // FIXME: account for labels not in the same line
-            let label_right = ann.label.as_ref().map_or(0, |l| str_width(l) + 1);
+            let label_right = ann
+                .label
+                .as_ref()
+                .map_or(0, |l| str_width(l, renderer.width()) + 1);
             label_right_margin = max(label_right_margin, ann.end.display + label_right);
         }
     }
-    let width_offset = 3 + max_line_num_len;
-    let code_offset = if multiline_depth == 0 {
-        width_offset
-    } else {
-        width_offset + multiline_depth + 1
-    };
-
-    let column_width = renderer.term_width.saturating_sub(code_offset);
-
     let margin = Margin::new(
         whitespace_margin,
         span_left_margin,
@@ -710,12 +954,22 @@ fn render_snippet_annotations(
         label_right_margin,
         column_width,
         max_line_len,
+        renderer.trim,
     );
 
     // Next, output the annotate source for this file
     for annotated_line_idx in 0..annotated_lines.len() {
         let previous_buffer_line = buffer.num_lines();
 
+        // A row of a soft wrapped line
+        let is_continuation = annotated_line_idx.checked_sub(1).is_some_and(|i| {
+            annotated_lines[i].line_index == annotated_lines[annotated_line_idx].line_index
+        });
+        let gutter = match snippet_gutter(snippet) {
+            Gutter::Hidden => Gutter::Hidden,
+            _ if is_continuation => Gutter::Continuation,
+            gutter => gutter,
+        };
         let depths = render_source_line(
             renderer,
             &annotated_lines[annotated_line_idx],
@@ -724,8 +978,9 @@ fn render_snippet_annotations(
             code_offset,
             max_line_num_len,
             margin,
-            !is_cont && annotated_line_idx + 1 == annotated_lines.len(),
-            snippet.line_numbering,
+            !is_cont && omitted == 0 && annotated_line_idx + 1 == annotated_lines.len(),
+            gutter,
+            snippet.visible_whitespace,
         );
 
         let mut to_add = BTreeMap::new();
@@ -748,13 +1003,22 @@ fn render_snippet_annotations(
         // check to see if we need to print out or elide lines that come between
         // this annotated line and the next one.
         if annotated_line_idx < (annotated_lines.len() - 1) {
-            let line_idx_delta = annotated_lines[annotated_line_idx + 1].line_index
-                - annotated_lines[annotated_line_idx].line_index;
-            match line_idx_delta.cmp(&2) {
-                Ordering::Greater => {
+            let line_index = annotated_lines[annotated_line_idx].line_index;
+            let next_line_index = annotated_lines[annotated_line_idx + 1].line_index;
+            let line_idx_delta = next_line_index - line_index;
+            match lone_line_between(line_index, next_line_index) {
+                None if line_idx_delta > 2 => {
                     let last_buffer_line_num = buffer.num_lines();
 
                     draw_line_separator(renderer, buffer, last_buffer_line_num, width_offset);
+                    if renderer.elided_line_count {
+                        buffer.puts(
+                            last_buffer_line_num,
+                            code_offset,
+                            &omitted_lines(line_idx_delta - 1, ""),
+                            ElementStyle::LineNumber,
+                        );
+                    }
 
                     // Set the multiline annotation vertical lines on `...` bridging line.
                     for (depth, style) in &multilines {
@@ -792,24 +1056,31 @@ fn render_snippet_annotations(
                     }
                 }
 
-                Ordering::Equal => {
-                    let unannotated_line = sm
-                        .get_line(annotated_lines[annotated_line_idx].line_index + 1)
-                        .unwrap_or("");
+                Some(line_index) => {
+                    let unannotated_line = sm.get_line(line_index).unwrap_or("");
+                    let unannotated_line = if snippet.visible_whitespace {
+                        Cow::Owned(show_whitespace(
+                            unannotated_line,
+                            sm.is_crlf(line_index),
+                            renderer.width(),
+                        ))
+                    } else {
+                        normalize_source(unannotated_line, renderer.width())
+                    };
 
                     let last_buffer_line_num = buffer.num_lines();
 
                     draw_line(
                         renderer,
                         buffer,
-                        &normalize_whitespace(unannotated_line),
-                        annotated_lines[annotated_line_idx + 1].line_index - 1,
+                        &unannotated_line,
+                        line_index,
                         last_buffer_line_num,
                         width_offset,
                         code_offset,
                         max_line_num_len,
                         margin,
-                        snippet.line_numbering,
+                        snippet_gutter(snippet),
                     );
 
                     for (depth, style) in &multilines {
@@ -843,12 +1114,30 @@ fn render_snippet_annotations(
                         }
                     }
                 }
-                Ordering::Less => {}
+                None => {}
             }
         }
 
         multilines.extend(to_add);
     }
+
+    // Summarize the lines past `Snippet::max_lines`
+    if omitted > 0 {
+        let line = buffer.num_lines();
+        draw_line_separator(renderer, buffer, line, width_offset);
+        for (depth, style) in &multilines {
+            draw_multiline_line(renderer, buffer, line, width_offset, *depth, *style, true);
+        }
+        buffer.puts(
+            line,
+            code_offset,
+            &omitted_lines(omitted, "more "),
+            ElementStyle::LineNumber,
+        );
+        if !is_cont {
+            draw_col_separator_end(renderer, buffer, line + 1, width_offset - 2);
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments, reason = "All arguments are necessary")]
@@ -861,7 +1150,8 @@ fn render_source_line(
     max_line_num_len: usize,
     margin: Margin,
     close_window: bool,
-    line_numbering: bool,
+    gutter: Gutter<'_>,
+    visible_whitespace: bool,
 ) -> Vec<(usize, ElementStyle)> {
     // Draw:
     //
@@ -877,7 +1167,20 @@ fn render_source_line(
     //   |  vertical divider between the column number and the code
     //   column number
 
-    let source_string = normalize_whitespace(line_info.line);
+    let mut source_string = if visible_whitespace {
+        Cow::Owned(show_whitespace(
+            line_info.line,
+            line_info.crlf,
+            renderer.width(),
+        ))
+    } else {
+        normalize_source(line_info.line, renderer.width())
+    };
+    if line_info.eof {
+        let source_string = source_string.to_mut();
+        source_string.push(' ');
+        source_string.push_str(EOF_MARKER);
+    }
 
     let line_offset = buffer.num_lines();
 
@@ -891,7 +1194,7 @@ fn render_source_line(
         code_offset,
         max_line_num_len,
         margin,
-        line_numbering,
+        gutter,
     );
 
     // If there are no annotations, we are done
@@ -1355,6 +1658,24 @@ fn render_source_line(
     //   |  _^  test
     for &(pos, annotation) in &annotations_position {
         let uline = renderer.decor_style.underline(annotation.is_primary());
+        if annotation.annotation_type == LineAnnotationType::Insertion {
+            // Only the right half when the point is at the start of the line
+            let [left_half, right_half] = renderer.decor_style.insertion_point();
+            let halves = if annotation.len() == 2 {
+                &[left_half, right_half][..]
+            } else {
+                &[right_half][..]
+            };
+            for (p, half) in (annotation.start.display..).zip(halves) {
+                buffer.putc(
+                    line_offset + 1,
+                    (code_offset + p).saturating_sub(left),
+                    *half,
+                    uline.style,
+                );
+            }
+            continue;
+        }
         for p in annotation.start.display..annotation.end.display {
             // The default span label underline.
             buffer.putc(
@@ -1425,8 +1746,8 @@ fn render_source_line(
         let width = annotation.end.display - annotation.start.display;
 
         static MIN_PAD: usize = 5;
-        let margin_width = str_width(renderer.decor_style.margin());
-        if width > margin.term_width * 2 && width > (MIN_PAD * 2 + margin_width) {
+        let margin_width = decor_width(renderer.decor_style.margin());
+        if margin.trims() && width > margin.term_width * 2 && width > (MIN_PAD * 2 + margin_width) {
             // If the terminal is *too* small, we keep at least a tiny bit of the span for
             // display.
             let pad = max(margin.term_width / 3, MIN_PAD);
@@ -1473,6 +1794,7 @@ fn emit_suggestion_default(
     sm: &SourceMap<'_>,
     primary_path: Option<&Cow<'_, str>>,
     matches_previous_suggestion: bool,
+    file: SuggestionFile,
     is_first: bool,
     is_cont: bool,
 ) {
@@ -1480,7 +1802,8 @@ fn emit_suggestion_default(
     let mut row_num = buffer_offset + usize::from(!matches_previous_suggestion);
     let is_multiline = spliced_lines.complete.lines().count() > 1;
 
-    if suggestion.path.as_ref() != primary_path
+    // Each file of a multi-file suggestion gets a header, to tell them apart
+    if (suggestion.path.as_ref() != primary_path || file != SuggestionFile::Single)
         && let Some(path) = suggestion.path.as_ref()
         && !matches_previous_suggestion
     {
@@ -1490,7 +1813,11 @@ fn emit_suggestion_default(
         for _ in 0..max_line_num_len {
             buffer.append(row_num - 1, " ", ElementStyle::NoStyle);
         }
-        let arrow = renderer.decor_style.file_start(is_first, false);
+        let arrow = if file == SuggestionFile::Next {
+            renderer.decor_style.secondary_file_start()
+        } else {
+            renderer.decor_style.file_start(is_first, false)
+        };
         buffer.append(row_num - 1, arrow, ElementStyle::LineNumber);
         let display_col = loc.char + 1;
         let message = if renderer.anonymized_origin_line_numbers {
@@ -1546,7 +1873,7 @@ fn emit_suggestion_default(
             buffer.puts(
                 row_num - 1 + line - line_start.line,
                 0,
-                &maybe_anonymized(renderer, line, max_line_num_len, suggestion.line_numbering),
+                &maybe_anonymized(renderer, line, max_line_num_len, snippet_gutter(suggestion)),
                 ElementStyle::LineNumber,
             );
             buffer.puts(
@@ -1558,7 +1885,7 @@ fn emit_suggestion_default(
             buffer.puts(
                 row_num - 1 + line - line_start.line,
                 max_line_num_len + 3,
-                &normalize_whitespace(sm.get_line(line).unwrap()),
+                &normalize_source(sm.get_line(line).unwrap(), renderer.width()),
                 ElementStyle::Removal,
             );
         }
@@ -1593,7 +1920,7 @@ fn emit_suggestion_default(
                     max_line_num_len,
                     &file_lines,
                     is_multiline,
-                    suggestion.line_numbering,
+                    snippet_gutter(suggestion),
                 );
             }),
             // Print first unhighlighted line, "..." and last unhighlighted line, like so:
@@ -1603,7 +1930,7 @@ fn emit_suggestion_default(
             // ...
             // LL | this line is just for context
             // LL | this line was highlighted
-            _ => {
+            n => {
                 let last_line = unhighlighted_lines.pop();
                 let first_line = unhighlighted_lines.drain(..).next();
 
@@ -1620,18 +1947,26 @@ fn emit_suggestion_default(
                         max_line_num_len,
                         &file_lines,
                         is_multiline,
-                        suggestion.line_numbering,
+                        snippet_gutter(suggestion),
                     );
                 }
 
                 let placeholder = renderer.decor_style.margin();
-                let padding = str_width(placeholder);
+                let padding = decor_width(placeholder);
                 buffer.puts(
                     row_num,
                     max_line_num_len.saturating_sub(padding),
                     placeholder,
                     ElementStyle::LineNumber,
                 );
+                if renderer.elided_line_count {
+                    buffer.puts(
+                        row_num,
+                        max_line_num_len + 3,
+                        &omitted_lines(n - 2, ""),
+                        ElementStyle::LineNumber,
+                    );
+                }
                 row_num += 1;
 
                 if let Some((p, l)) = last_line {
@@ -1647,7 +1982,7 @@ fn emit_suggestion_default(
                         max_line_num_len,
                         &file_lines,
                         is_multiline,
-                        suggestion.line_numbering,
+                        snippet_gutter(suggestion),
                     );
                 }
             }
@@ -1664,7 +1999,7 @@ fn emit_suggestion_default(
             max_line_num_len,
             &file_lines,
             is_multiline,
-            suggestion.line_numbering,
+            snippet_gutter(suggestion),
         );
     }
 
@@ -1695,11 +2030,14 @@ fn emit_suggestion_default(
             };
             // ...or trailing spaces. Account for substitutions containing unicode
             // characters.
-            let sub_len: usize = str_width(if is_whitespace_addition {
-                &part.replacement
-            } else {
-                part.replacement.trim()
-            });
+            let sub_len: usize = str_width(
+                if is_whitespace_addition {
+                    &part.replacement
+                } else {
+                    part.replacement.trim()
+                },
+                renderer.width(),
+            );
 
             let offset: isize = offsets
                 .iter()
@@ -1733,7 +2071,7 @@ fn emit_suggestion_default(
             }
 
             // length of the code after substitution
-            let full_sub_len = str_width(&part.replacement).cast_signed();
+            let full_sub_len = str_width(&part.replacement, renderer.width()).cast_signed();
 
             // length of the code to be substituted
             let snippet_len = span_end_pos.cast_signed() - span_start_pos.cast_signed();
@@ -1748,7 +2086,7 @@ fn emit_suggestion_default(
     // if we elided some lines, add an ellipsis
     if lines.next().is_some() {
         let placeholder = renderer.decor_style.margin();
-        let padding = str_width(placeholder);
+        let padding = decor_width(placeholder);
         buffer.puts(
             row_num,
             max_line_num_len.saturating_sub(padding),
@@ -1783,7 +2121,7 @@ fn draw_code_line(
     max_line_num_len: usize,
     file_lines: &[&LineInfo<'_>],
     is_multiline: bool,
-    line_numbering: bool,
+    gutter: Gutter<'_>,
 ) {
     if let DisplaySuggestion::Diff = show_code_change {
         // We need to print more than one line if the span we need to remove is multiline.
@@ -1793,7 +2131,7 @@ fn draw_code_line(
             buffer.puts(
                 *row_num - 1,
                 0,
-                &maybe_anonymized(renderer, line_num + index, max_line_num_len, line_numbering),
+                &maybe_anonymized(renderer, line_num + index, max_line_num_len, gutter),
                 ElementStyle::LineNumber,
             );
             buffer.puts(
@@ -1802,7 +2140,7 @@ fn draw_code_line(
                 "- ",
                 ElementStyle::Removal,
             );
-            let line = normalize_whitespace(line_to_remove.line);
+            let line = normalize_source(line_to_remove.line, renderer.width());
             buffer.puts(
                 *row_num - 1,
                 max_line_num_len + 3,
@@ -1846,7 +2184,7 @@ fn draw_code_line(
                     renderer,
                     line_num + file_lines.len() - 1,
                     max_line_num_len,
-                    line_numbering,
+                    gutter,
                 ),
                 ElementStyle::LineNumber,
             );
@@ -1859,7 +2197,7 @@ fn draw_code_line(
             buffer.puts(
                 *row_num - 1,
                 max_line_num_len + 3,
-                &normalize_whitespace(last_line.line),
+                &normalize_source(last_line.line, renderer.width()),
                 ElementStyle::NoStyle,
             );
             style_substitution_highlights(
@@ -1890,13 +2228,13 @@ fn draw_code_line(
                 buffer.puts(
                     *row_num,
                     0,
-                    &maybe_anonymized(renderer, line_num, max_line_num_len, line_numbering),
+                    &maybe_anonymized(renderer, line_num, max_line_num_len, gutter),
                     ElementStyle::LineNumber,
                 );
                 buffer.puts(*row_num, max_line_num_len + 1, "+ ", ElementStyle::Addition);
                 buffer.append(
                     *row_num,
-                    &normalize_whitespace(line_to_add),
+                    &normalize_source(line_to_add, renderer.width()),
                     ElementStyle::NoStyle,
                 );
             }
@@ -1905,7 +2243,7 @@ fn draw_code_line(
         buffer.puts(
             *row_num,
             0,
-            &maybe_anonymized(renderer, line_num, max_line_num_len, line_numbering),
+            &maybe_anonymized(renderer, line_num, max_line_num_len, gutter),
             ElementStyle::LineNumber,
         );
         match &highlight_parts {
@@ -1934,33 +2272,33 @@ fn draw_code_line(
         buffer.puts(
             *row_num,
             max_line_num_len + 3,
-            &normalize_whitespace(line_to_add),
+            &normalize_source(line_to_add, renderer.width()),
             ElementStyle::NoStyle,
         );
     } else if let DisplaySuggestion::Add = show_code_change {
         buffer.puts(
             *row_num,
             0,
-            &maybe_anonymized(renderer, line_num, max_line_num_len, line_numbering),
+            &maybe_anonymized(renderer, line_num, max_line_num_len, gutter),
             ElementStyle::LineNumber,
         );
         buffer.puts(*row_num, max_line_num_len + 1, "+ ", ElementStyle::Addition);
         buffer.append(
             *row_num,
-            &normalize_whitespace(line_to_add),
+            &normalize_source(line_to_add, renderer.width()),
             ElementStyle::NoStyle,
         );
     } else {
         buffer.puts(
             *row_num,
             0,
-            &maybe_anonymized(renderer, line_num, max_line_num_len, line_numbering),
+            &maybe_anonymized(renderer, line_num, max_line_num_len, gutter),
             ElementStyle::LineNumber,
         );
         draw_col_separator(renderer, buffer, *row_num, max_line_num_len + 1);
         buffer.append(
             *row_num,
-            &normalize_whitespace(line_to_add),
+            &normalize_source(line_to_add, renderer.width()),
             ElementStyle::NoStyle,
         );
     }
@@ -2014,23 +2352,26 @@ fn draw_line(
     code_offset: usize,
     max_line_num_len: usize,
     margin: Margin,
-    line_numbering: bool,
+    gutter: Gutter<'_>,
 ) -> usize {
     // Tabs are assumed to have been replaced by spaces in calling code.
     debug_assert!(!source_string.contains('\t'));
-    let line_len = str_width(source_string);
+    let width = renderer.width();
+    let line_len = str_width(source_string, width);
     // Create the source line we will highlight.
     let mut left = margin.left(line_len);
     let right = margin.right(line_len);
 
     let mut taken = 0;
     let mut skipped = 0;
-    let code: String = source_string
-        .chars()
-        .skip_while(|ch| {
-            let w = char_width(*ch);
-            // If `skipped` is less than `left`, always skip the next `ch`,
-            // even if `ch` is a multi-width char that would make `skipped`
+    let code: String = width
+        .clusters(source_string)
+        .into_iter()
+        .map(|(_, cluster)| cluster)
+        .skip_while(|cluster| {
+            let w = cluster_width(cluster, renderer.ambiguous_width);
+            // If `skipped` is less than `left`, always skip the next `cluster`,
+            // even if `cluster` is multi-width that would make `skipped`
             // exceed `left`. This ensures that we do not exceed term width on
             // source lines.
             if skipped < left {
@@ -2040,9 +2381,9 @@ fn draw_line(
                 false
             }
         })
-        .take_while(|ch| {
+        .take_while(|cluster| {
             // Make sure that the trimming on the right will fall within the terminal width.
-            taken += char_width(*ch);
+            taken += cluster_width(cluster, renderer.ambiguous_width);
             taken <= (right - left)
         })
         .collect();
@@ -2051,14 +2392,14 @@ fn draw_line(
         left += skipped - left;
     }
     let placeholder = renderer.decor_style.margin();
-    let padding = str_width(placeholder);
+    let padding = decor_width(placeholder);
     let (width_taken, bytes_taken) = if margin.was_cut_left() {
         // We have stripped some code/whitespace from the beginning, make it clear.
         let mut bytes_taken = 0;
         let mut width_taken = 0;
-        for ch in code.chars() {
-            width_taken += char_width(ch);
-            bytes_taken += ch.len_utf8();
+        for (_, cluster) in width.clusters(&code) {
+            width_taken += cluster_width(cluster, renderer.ambiguous_width);
+            bytes_taken += cluster.len();
 
             if width_taken >= padding {
                 break;
@@ -2087,9 +2428,9 @@ fn draw_line(
         // We have stripped some code/whitespace from the beginning, make it clear.
         let mut char_taken = 0;
         let mut width_taken_inner = 0;
-        for ch in code.chars().rev() {
-            width_taken_inner += char_width(ch);
-            char_taken += 1;
+        for (_, cluster) in width.clusters(&code).into_iter().rev() {
+            width_taken_inner += cluster_width(cluster, renderer.ambiguous_width);
+            char_taken += cluster.chars().count();
 
             if width_taken_inner >= padding {
                 break;
@@ -2107,7 +2448,7 @@ fn draw_line(
     buffer.puts(
         line_offset,
         0,
-        &maybe_anonymized(renderer, line_index, max_line_num_len, line_numbering),
+        &maybe_anonymized(renderer, line_index, max_line_num_len, gutter),
         ElementStyle::LineNumber,
     );
 
@@ -2253,18 +2594,17 @@ fn maybe_anonymized(
     renderer: &Renderer,
     line_num: usize,
     max_line_num_len: usize,
-    line_numbering: bool,
+    gutter: Gutter<'_>,
 ) -> String {
-    format!(
-        "{:>max_line_num_len$}",
-        if !line_numbering {
-            Cow::Borrowed("")
-        } else if renderer.anonymized_snippet_line_numbers {
-            Cow::Borrowed(ANONYMIZED_LINE_NUM)
-        } else {
-            Cow::Owned(line_num.to_string())
-        }
-    )
+    let label = match gutter {
+        Gutter::Hidden => Cow::Borrowed(""),
+        Gutter::Continuation => Cow::Borrowed(renderer.decor_style.wrap_marker()),
+        _ if renderer.anonymized_snippet_line_numbers => Cow::Borrowed(ANONYMIZED_LINE_NUM),
+        Gutter::LineNumbers => Cow::Owned(line_num.to_string()),
+        Gutter::Labels(labels) => Cow::Owned(labels.label(line_num)),
+    };
+    let padding = max_line_num_len.saturating_sub(str_width(&label, renderer.width()));
+    format!("{}{label}", " ".repeat(padding))
 }
 
 fn draw_note_separator(
@@ -2286,6 +2626,14 @@ fn draw_line_separator(renderer: &Renderer, buffer: &mut StyledBuffer, line: usi
     buffer.puts(line, column, dots, ElementStyle::LineNumber);
 }
 
+/// `37 lines omitted`, for `count` lines that are not shown
+///
+/// It goes next to the `...` that already marks the gap, so it has no dots of its own.
+fn omitted_lines(count: usize, qualifier: &str) -> String {
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {qualifier}line{plural} omitted")
+}
+
 trait MessageOrTitle {
     fn level(&self) -> &Level<'_>;
     fn id(&self) -> Option<&Id<'_>>;
@@ -2357,11 +2705,84 @@ fn num_decimal_digits(num: Option<usize>) -> usize {
     MAX_DIGITS
 }
 
-fn str_width(s: &str) -> usize {
-    s.chars().map(char_width).sum()
+/// How source text is split into columns, see [`Renderer::grapheme_clusters`] and
+/// [`Renderer::ambiguous_width`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Width {
+    pub(crate) ambiguous: AmbiguousWidth,
+    pub(crate) grapheme_clusters: bool,
+}
+
+impl Width {
+    /// The renderer's own glyphs, which take one column each in the [`StyledBuffer`]
+    const DECOR: Self = Self {
+        ambiguous: AmbiguousWidth::Narrow,
+        grapheme_clusters: false,
+    };
+
+    /// The grapheme clusters of `s` with their byte offsets, or its `char`s when not
+    /// segmenting by cluster
+    pub(crate) fn clusters(self, s: &str) -> Vec<(usize, &str)> {
+        if self.grapheme_clusters {
+            s.grapheme_indices(true).collect()
+        } else {
+            s.char_indices()
+                .map(|(i, ch)| (i, &s[i..i + ch.len_utf8()]))
+                .collect()
+        }
+    }
+}
+
+pub(crate) fn str_width(s: &str, width: Width) -> usize {
+    if width.grapheme_clusters {
+        s.graphemes(true)
+            .map(|cluster| cluster_width(cluster, width.ambiguous))
+            .sum()
+    } else {
+        s.chars().map(|ch| char_width(ch, width.ambiguous)).sum()
+    }
+}
+
+/// The display width of a grapheme cluster, e.g. `e` with a combining accent or an emoji joined
+/// with zero width joiners
+pub(crate) fn cluster_width(cluster: &str, ambiguous_width: AmbiguousWidth) -> usize {
+    let mut chars = cluster.chars();
+    match (chars.next(), chars.next(), ambiguous_width) {
+        (Some(ch), None, _) => char_width(ch, ambiguous_width),
+        (_, _, AmbiguousWidth::Narrow) => unicode_width::UnicodeWidthStr::width(cluster),
+        (Some(ch), _, AmbiguousWidth::Wide) if is_ambiguous_letter(ch) => {
+            unicode_width::UnicodeWidthStr::width_cjk(cluster) + 1
+        }
+        (_, _, AmbiguousWidth::Wide) => unicode_width::UnicodeWidthStr::width_cjk(cluster),
+    }
+}
+
+/// The width of the renderer's own glyphs, like [`DecorStyle::margin`][super::DecorStyle]
+///
+/// They take one column each in the [`StyledBuffer`], whatever [`AmbiguousWidth`] the source
+/// is measured with.
+fn decor_width(decor: &str) -> usize {
+    str_width(decor, Width::DECOR)
 }
 
-pub(crate) fn char_width(ch: char) -> usize {
+/// The Greek and Cyrillic letters that are East Asian ambiguous width
+///
+/// `unicode_width` treats them as narrow even in a CJK context, unlike terminals.
+const AMBIGUOUS_LETTERS: [RangeInclusive<char>; 7] = [
+    '\u{0391}'..='\u{03A1}',
+    '\u{03A3}'..='\u{03A9}',
+    '\u{03B1}'..='\u{03C1}',
+    '\u{03C3}'..='\u{03C9}',
+    '\u{0401}'..='\u{0401}',
+    '\u{0410}'..='\u{044F}',
+    '\u{0451}'..='\u{0451}',
+];
+
+fn is_ambiguous_letter(ch: char) -> bool {
+    AMBIGUOUS_LETTERS.iter().any(|range| range.contains(&ch))
+}
+
+pub(crate) fn char_width(ch: char, ambiguous_width: AmbiguousWidth) -> usize {
     // FIXME: `unicode_width` sometimes disagrees with terminals on how wide a `char` is. For now,
     // just accept that sometimes the code line will be longer than desired.
     match ch {
@@ -2376,7 +2797,12 @@ pub(crate) fn char_width(ch: char) -> usize {
         | '\u{001A}' | '\u{001B}' | '\u{001C}' | '\u{001D}' | '\u{001E}' | '\u{001F}'
         | '\u{007F}' | '\u{202A}' | '\u{202B}' | '\u{202D}' | '\u{202E}' | '\u{2066}'
         | '\u{2067}' | '\u{2068}' | '\u{202C}' | '\u{2069}' => 1,
-        _ => unicode_width::UnicodeWidthChar::width(ch).unwrap_or(1),
+        _ => match ambiguous_width {
+            AmbiguousWidth::Narrow => unicode_width::UnicodeWidthChar::width(ch),
+            AmbiguousWidth::Wide if is_ambiguous_letter(ch) => Some(2),
+            AmbiguousWidth::Wide => unicode_width::UnicodeWidthChar::width_cjk(ch),
+        }
+        .unwrap_or(1),
     }
 }
 
@@ -2406,6 +2832,10 @@ pub(crate) enum LineAnnotationType {
     /// Annotation under a single line of code
     Singleline,
 
+    /// Annotation between two characters of a single line of code, covering the columns on
+    /// both sides
+    Insertion,
+
     // The Multiline type above is replaced with the following three in order
     // to reuse the current label drawing code.
     //
@@ -2504,12 +2934,22 @@ pub(crate) enum DisplaySuggestion {
 }
 
 impl DisplaySuggestion {
-    fn new(complete: &str, patches: &[TrimmedPatch<'_>], sm: &SourceMap<'_>) -> Self {
+    fn new(
+        complete: &str,
+        patches: &[TrimmedPatch<'_>],
+        sm: &SourceMap<'_>,
+        style: SuggestionStyle,
+    ) -> Self {
         let has_deletion = patches
             .iter()
             .any(|p| p.is_deletion(sm) || p.is_destructive_replacement(sm));
         let is_multiline = complete.lines().count() > 1;
-        if has_deletion && !is_multiline {
+        // Multiline suggestions are always shown line by line
+        if style == SuggestionStyle::Diff && !is_multiline {
+            Self::Diff
+        } else if style == SuggestionStyle::Underline && !is_multiline {
+            Self::Underline
+        } else if has_deletion && !is_multiline {
             Self::Diff
         } else if patches.len() == 1
             && patches.first().is_some_and(|p| {
@@ -2579,10 +3019,23 @@ const OUTPUT_REPLACEMENTS: &[(char, &str)] = &[
 ];
 
 pub(crate) fn normalize_whitespace(s: &str) -> Cow<'_, str> {
-    if !s
-        .chars()
-        .any(|user| OUTPUT_REPLACEMENTS.iter().any(|(bad, _)| user == *bad))
-    {
+    normalize(s, false)
+}
+
+/// [`normalize_whitespace`] for source code, which keeps ZWJ when it is measured by grapheme
+/// cluster
+fn normalize_source(s: &str, width: Width) -> Cow<'_, str> {
+    normalize(s, width.grapheme_clusters)
+}
+
+fn normalize(s: &str, keep_zwj: bool) -> Cow<'_, str> {
+    let replaced = |c: char| {
+        (!keep_zwj || c != '\u{200d}')
+            && OUTPUT_REPLACEMENTS
+                .binary_search_by_key(&c, |(k, _)| *k)
+                .is_ok()
+    };
+    if !s.chars().any(replaced) {
         return Cow::Borrowed(s);
     }
 
@@ -2591,7 +3044,7 @@ pub(crate) fn normalize_whitespace(s: &str) -> Cow<'_, str> {
     // Otherwise, retain the input char.
     let normalized = s.chars().fold(String::with_capacity(s.len()), |mut s, c| {
         match OUTPUT_REPLACEMENTS.binary_search_by_key(&c, |(k, _)| *k) {
-            Ok(i) => s.push_str(OUTPUT_REPLACEMENTS[i].1),
+            Ok(i) if replaced(c) => s.push_str(OUTPUT_REPLACEMENTS[i].1),
             _ => s.push(c),
         }
         s
@@ -2599,6 +3052,22 @@ pub(crate) fn normalize_whitespace(s: &str) -> Cow<'_, str> {
     Cow::Owned(normalized)
 }
 
+/// [`normalize_whitespace`], but with spaces, tabs and a `\r\n` line ending shown as glyphs
+/// as wide as what they replace
+///
+/// `·` and `→` are East Asian ambiguous width, so ASCII stands in for them when that is wide.
+fn show_whitespace(s: &str, crlf: bool, width: Width) -> String {
+    let (space, tab) = match width.ambiguous {
+        AmbiguousWidth::Narrow => ("·", "→   "),
+        AmbiguousWidth::Wide => (".", ">   "),
+    };
+    let mut shown = s.replace(' ', space).replace('\t', tab);
+    if crlf {
+        shown.push('␍');
+    }
+    normalize_source(&shown, width).into_owned()
+}
+
 #[derive(Clone, Copy, Debug, PartialOrd, Ord, PartialEq, Eq)]
 pub(crate) enum ElementStyle {
     MainHeaderMsg,
@@ -2666,6 +3135,15 @@ struct PreProcessedGroup<'a> {
     max_depth: usize,
 }
 
+/// Where a suggestion is within a [`MultiFileSuggestion`][crate::MultiFileSuggestion]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SuggestionFile {
+    /// Not part of one
+    Single,
+    First,
+    Next,
+}
+
 enum PreProcessedElement<'a> {
     Message(&'a Message<'a>),
     Cause(
@@ -2673,6 +3151,7 @@ enum PreProcessedElement<'a> {
             &'a Snippet<'a, Annotation<'a>>,
             SourceMap<'a>,
             Vec<AnnotatedLineInfo<'a>>,
+            usize,
         ),
     ),
     Suggestion(
@@ -2681,6 +3160,7 @@ enum PreProcessedElement<'a> {
             SourceMap<'a>,
             SplicedLines<'a>,
             DisplaySuggestion,
+            SuggestionFile,
         ),
     ),
     Origin(&'a Origin<'a>),
@@ -2688,30 +3168,50 @@ enum PreProcessedElement<'a> {
 }
 
 fn pre_process<'a>(
+    renderer: &Renderer,
     groups: &'a [Group<'a>],
 ) -> (
     Option<usize>,
+    usize,
     Option<&'a Cow<'a, str>>,
     Vec<PreProcessedGroup<'a>>,
 ) {
     let mut max_line_num = None;
+    let mut max_label_len = 0;
     let mut report_primary_path = None;
     let mut out = Vec::with_capacity(groups.len());
     for group in groups {
         let mut elements = Vec::with_capacity(group.elements.len());
         let mut primary_path = None;
         let mut max_depth = 0;
-        for element in &group.elements {
+        let inline_suggestion = group.inline_suggestion().map(|(i, _)| i);
+        for (i, element) in group.elements.iter().enumerate() {
             match element {
                 Element::Message(message) => {
                     elements.push(PreProcessedElement::Message(message));
                 }
                 Element::Cause(cause) => {
-                    let sm = SourceMap::new(&cause.source, cause.line_start);
-                    let (depth, annotated_lines) =
-                        sm.annotated_lines(cause.markers.clone(), cause.fold);
-
-                    if cause.fold {
+                    let sm = cause_source_map(renderer, cause);
+                    let (depth, mut annotated_lines) = sm.annotated_lines(cause);
+                    let omitted = cause.max_lines.map_or(0, |max_lines| {
+                        truncate_annotated_lines(&mut annotated_lines, max_lines)
+                    });
+
+                    // Excerpts only have the lines around their annotations
+                    if let Gutter::Labels(labels) = snippet_gutter(cause) {
+                        // Including lone lines shown between annotated lines
+                        let gaps = annotated_lines.windows(2).filter_map(|pair| {
+                            lone_line_between(pair[0].line_index, pair[1].line_index)
+                        });
+                        let lines = annotated_lines.iter().map(|l| l.line_index).chain(gaps);
+                        max_label_len =
+                            max(max_label_len, max_label_width(renderer, labels, lines));
+                    } else if omitted > 0 || !cause.chunks.is_empty() {
+                        if cause.line_numbering {
+                            let last_line = annotated_lines.last().map_or(0, |l| l.line_index);
+                            max_line_num = Some(max(last_line, max_line_num.unwrap_or(0)));
+                        }
+                    } else if cause.fold {
                         let end = cause
                             .markers
                             .iter()
@@ -2719,10 +3219,15 @@ fn pre_process<'a>(
                             .max()
                             .unwrap_or(cause.source.len())
                             .min(cause.source.len());
+                        // Context lines can come after the last annotation
+                        let last_line = annotated_lines.last().map_or(0, |l| l.line_index);
 
                         if cause.line_numbering {
                             max_line_num = Some(max(
-                                cause.line_start + newline_count(&cause.source[..end]),
+                                max(
+                                    cause.line_start + newline_count(&cause.source[..end]),
+                                    last_line,
+                                ),
                                 max_line_num.unwrap_or(0),
                             ));
                         }
@@ -2739,53 +3244,42 @@ fn pre_process<'a>(
                         primary_path = Some(cause.path.as_ref());
                     }
                     max_depth = max(depth, max_depth);
-                    elements.push(PreProcessedElement::Cause((cause, sm, annotated_lines)));
+                    elements.push(PreProcessedElement::Cause((
+                        cause,
+                        sm,
+                        annotated_lines,
+                        omitted,
+                    )));
                 }
+                // Shown in the title instead
+                Element::Suggestion(_) if inline_suggestion == Some(i) => {}
                 Element::Suggestion(suggestion) => {
-                    let sm = SourceMap::new(&suggestion.source, suggestion.line_start);
-                    if let Some(spliced_lines) =
-                        sm.splice_lines(suggestion.markers.clone(), suggestion.fold)
-                    {
-                        let display_suggestion = DisplaySuggestion::new(
-                            &spliced_lines.complete,
-                            &spliced_lines.patches,
-                            &sm,
-                        );
-
-                        if suggestion.line_numbering {
-                            if suggestion.fold {
-                                if let Some(first) = spliced_lines.patches.first() {
-                                    let (l_start, _) =
-                                        sm.span_to_locations(first.original_span.clone());
-                                    let nc = newline_count(&spliced_lines.complete);
-                                    let sugg_max_line_num = match display_suggestion {
-                                        DisplaySuggestion::Underline => l_start.line,
-                                        DisplaySuggestion::Diff => {
-                                            let file_lines = sm.span_to_lines(first.span.clone());
-                                            file_lines
-                                                .last()
-                                                .map_or(l_start.line + nc, |line| line.line_index)
-                                        }
-                                        DisplaySuggestion::None => l_start.line + nc,
-                                        DisplaySuggestion::Add => l_start.line + nc,
-                                    };
-                                    max_line_num =
-                                        Some(max(sugg_max_line_num, max_line_num.unwrap_or(0)));
-                                }
-                            } else {
-                                max_line_num = Some(max(
-                                    suggestion.line_start + newline_count(&spliced_lines.complete),
-                                    max_line_num.unwrap_or(0),
-                                ));
-                            }
+                    if let Some(element) = pre_process_suggestion(
+                        renderer,
+                        suggestion,
+                        SuggestionFile::Single,
+                        &mut max_line_num,
+                        &mut max_label_len,
+                    ) {
+                        elements.push(element);
+                    }
+                }
+                Element::MultiFileSuggestion(suggestion) => {
+                    for (j, file) in suggestion.files.iter().enumerate() {
+                        let position = if j == 0 {
+                            SuggestionFile::First
+                        } else {
+                            SuggestionFile::Next
+                        };
+                        if let Some(element) = pre_process_suggestion(
+                            renderer,
+                            file,
+                            position,
+                            &mut max_line_num,
+                            &mut max_label_len,
+                        ) {
+                            elements.push(element);
                         }
-
-                        elements.push(PreProcessedElement::Suggestion((
-                            suggestion,
-                            sm,
-                            spliced_lines,
-                            display_suggestion,
-                        )));
                     }
                 }
                 Element::Origin(origin) => {
@@ -2811,7 +3305,250 @@ fn pre_process<'a>(
         out.push(group);
     }
 
-    (max_line_num, report_primary_path, out)
+    (max_line_num, max_label_len, report_primary_path, out)
+}
+
+/// What a [`Snippet`] shows in its gutter
+#[derive(Clone, Copy, Debug)]
+enum Gutter<'a> {
+    Hidden,
+    LineNumbers,
+    Labels(&'a dyn LineLabels),
+    /// A row continuing a soft wrapped line, see [`Renderer::soft_wrap`]
+    Continuation,
+}
+
+fn snippet_gutter<'a, T>(snippet: &Snippet<'a, T>) -> Gutter<'a> {
+    match snippet.line_labels {
+        _ if !snippet.line_numbering => Gutter::Hidden,
+        Some(labels) => Gutter::Labels(labels),
+        None => Gutter::LineNumbers,
+    }
+}
+
+/// The width of the widest label of `lines`
+fn max_label_width(
+    renderer: &Renderer,
+    labels: &dyn LineLabels,
+    lines: impl IntoIterator<Item = usize>,
+) -> usize {
+    lines
+        .into_iter()
+        .map(|line| str_width(&labels.label(line), renderer.width()))
+        .max()
+        .unwrap_or(0)
+}
+
+fn cause_source_map<'a>(
+    renderer: &Renderer,
+    cause: &'a Snippet<'a, Annotation<'a>>,
+) -> SourceMap<'a> {
+    if cause.chunks.is_empty() {
+        SourceMap::new(&cause.source, cause.line_start, renderer.width())
+    } else {
+        SourceMap::with_chunks(&cause.source, &cause.chunks, renderer.width())
+    }
+}
+
+/// Split the lines wider than `width` into rows, for [`Renderer::soft_wrap`]
+///
+/// Each row is shown as a line of its own, with the same line index as the line it is part of.
+/// The lone lines shown between annotated lines are included, so they are wrapped too.
+fn wrap_annotated_lines<'a>(
+    renderer: &Renderer,
+    sm: &SourceMap<'a>,
+    annotated_lines: &[AnnotatedLineInfo<'a>],
+    width: usize,
+) -> Vec<AnnotatedLineInfo<'a>> {
+    let mut wrapped = Vec::with_capacity(annotated_lines.len());
+    for (i, line_info) in annotated_lines.iter().enumerate() {
+        wrap_line(renderer, line_info, width, &mut wrapped);
+        if let Some(next) = annotated_lines.get(i + 1)
+            && let Some(line_index) = lone_line_between(line_info.line_index, next.line_index)
+        {
+            let gap = AnnotatedLineInfo {
+                line: sm.get_line(line_index).unwrap_or(""),
+                line_index,
+                annotations: vec![],
+                keep: false,
+                crlf: sm.is_crlf(line_index),
+                eof: false,
+            };
+            wrap_line(renderer, &gap, width, &mut wrapped);
+        }
+    }
+    wrapped
+}
+
+/// Split `line_info` into rows at most `width` wide, moving each annotation to the rows it
+/// covers
+///
+/// An annotation over several rows is underlined on each of them and labeled on the last.
+fn wrap_line<'a>(
+    renderer: &Renderer,
+    line_info: &AnnotatedLineInfo<'a>,
+    width: usize,
+    wrapped: &mut Vec<AnnotatedLineInfo<'a>>,
+) {
+    // The byte, char and display column each row starts at
+    let mut rows = vec![(0, 0, 0)];
+    let (mut char_pos, mut display_pos, mut row_width) = (0, 0, 0);
+    for (byte, cluster) in renderer.width().clusters(line_info.line) {
+        let cluster_width = cluster_width(cluster, renderer.ambiguous_width);
+        if 0 < row_width && width < row_width + cluster_width {
+            rows.push((byte, char_pos, display_pos));
+            row_width = 0;
+        }
+        char_pos += cluster.chars().count();
+        display_pos += cluster_width;
+        row_width += cluster_width;
+    }
+
+    for (row, &(byte, char_start, display_start)) in rows.iter().enumerate() {
+        let next = rows.get(row + 1);
+        let byte_end = next.map_or(line_info.line.len(), |(b, _, _)| *b);
+        let char_end = next.map_or(usize::MAX, |(_, c, _)| *c);
+        let display_end = next.map_or(usize::MAX, |(_, _, d)| *d);
+
+        let mut annotations = vec![];
+        for ann in &line_info.annotations {
+            // The vertical line passes through every row
+            if let LineAnnotationType::MultilineLine(_) = ann.annotation_type {
+                annotations.push(ann.clone());
+                continue;
+            }
+            let (start, end) = (ann.start.char, ann.end.char);
+            let covered = if start == end {
+                char_start <= start && start < char_end
+            } else {
+                start < char_end && char_start < end
+            };
+            if !covered {
+                continue;
+            }
+            let mut ann = ann.clone();
+            if ann.start.char < char_start {
+                ann.start.char = char_start;
+                ann.start.display = display_start;
+            }
+            if char_end < ann.end.char {
+                ann.end.char = char_end;
+                ann.end.display = display_end;
+                // Labeled on the row the annotation ends on
+                ann.label = None;
+            }
+            ann.start.char -= char_start;
+            ann.start.display -= display_start;
+            ann.end.char -= char_start;
+            ann.end.display -= display_start;
+            annotations.push(ann);
+        }
+        wrapped.push(AnnotatedLineInfo {
+            line: &line_info.line[byte..byte_end],
+            line_index: line_info.line_index,
+            annotations,
+            keep: line_info.keep,
+            // The line ending is on the last row
+            crlf: line_info.crlf && next.is_none(),
+            eof: line_info.eof && next.is_none(),
+        });
+    }
+}
+
+/// The line between two shown lines, when it is the only one, which is rendered instead of
+/// `...`
+fn lone_line_between(line_index: usize, next_line_index: usize) -> Option<usize> {
+    (next_line_index == line_index + 2).then_some(line_index + 1)
+}
+
+/// Drop the lines that would be rendered after the first `max_lines`, returning how many
+/// would have been rendered
+fn truncate_annotated_lines(
+    annotated_lines: &mut Vec<AnnotatedLineInfo<'_>>,
+    max_lines: usize,
+) -> usize {
+    let rendered = annotated_lines
+        .iter()
+        .scan(None, |previous: &mut Option<usize>, line| {
+            let gap = previous.is_some_and(|p| lone_line_between(p, line.line_index).is_some());
+            *previous = Some(line.line_index);
+            Some(1 + usize::from(gap))
+        })
+        .collect::<Vec<_>>();
+    let mut total = 0;
+    let mut end = rendered.len();
+    for (i, lines) in rendered.iter().enumerate() {
+        if total + lines > max_lines {
+            end = i;
+            break;
+        }
+        total += lines;
+    }
+    annotated_lines.truncate(end);
+    rendered.iter().sum::<usize>() - total
+}
+
+fn pre_process_suggestion<'a>(
+    renderer: &Renderer,
+    suggestion: &'a Snippet<'a, Patch<'a>>,
+    file: SuggestionFile,
+    max_line_num: &mut Option<usize>,
+    max_label_len: &mut usize,
+) -> Option<PreProcessedElement<'a>> {
+    if suggestion.suggestion_style == SuggestionStyle::Hidden {
+        return None;
+    }
+    let sm = SourceMap::new(&suggestion.source, suggestion.line_start, renderer.width());
+    let spliced_lines = sm.splice_lines(suggestion.markers.clone(), suggestion.fold)?;
+    let display_suggestion = DisplaySuggestion::new(
+        &spliced_lines.complete,
+        &spliced_lines.patches,
+        &sm,
+        suggestion.suggestion_style,
+    );
+
+    let gutter = snippet_gutter(suggestion);
+    if !matches!(gutter, Gutter::Hidden) {
+        let lines = if suggestion.fold {
+            spliced_lines.patches.first().map(|first| {
+                let (l_start, _) = sm.span_to_locations(first.original_span.clone());
+                let nc = newline_count(&spliced_lines.complete);
+                let sugg_max_line_num = match display_suggestion {
+                    DisplaySuggestion::Underline => l_start.line,
+                    DisplaySuggestion::Diff => {
+                        let file_lines = sm.span_to_lines(first.span.clone());
+                        file_lines
+                            .last()
+                            .map_or(l_start.line + nc, |line| line.line_index)
+                    }
+                    DisplaySuggestion::None => l_start.line + nc,
+                    DisplaySuggestion::Add => l_start.line + nc,
+                };
+                l_start.line..=sugg_max_line_num
+            })
+        } else {
+            Some(
+                suggestion.line_start
+                    ..=suggestion.line_start + newline_count(&spliced_lines.complete),
+            )
+        };
+        if let Some(lines) = lines {
+            match gutter {
+                Gutter::Labels(labels) => {
+                    *max_label_len = max(*max_label_len, max_label_width(renderer, labels, lines));
+                }
+                _ => *max_line_num = Some(max(*lines.end(), max_line_num.unwrap_or(0))),
+            }
+        }
+    }
+
+    Some(PreProcessedElement::Suggestion((
+        suggestion,
+        sm,
+        spliced_lines,
+        display_suggestion,
+        file,
+    )))
 }
 
 fn newline_count(body: &str) -> usize {
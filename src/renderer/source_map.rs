@@ -1,20 +1,55 @@
 use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::format;
 use alloc::string::String;
 use alloc::{vec, vec::Vec};
 use core::cmp::{max, min};
 use core::ops::Range;
 
-use crate::renderer::{LineAnnotation, LineAnnotationType, char_width, num_overlap};
-use crate::{Annotation, AnnotationKind, Patch};
+use crate::renderer::{LineAnnotation, LineAnnotationType, Width, cluster_width, num_overlap};
+use crate::source::SourceChunk;
+use crate::{Annotation, AnnotationKind, Patch, Snippet};
+
+/// Characters that change how the source around them is shown without being visible
+/// themselves, with their names, for [`Snippet::reveal_invisible`]
+///
+/// Not ZERO WIDTH JOINER and ZERO WIDTH NON-JOINER, which are part of emoji and some scripts.
+const INVISIBLE_CHARS: &[(char, &str)] = &[
+    ('\u{00AD}', "SOFT HYPHEN"),
+    ('\u{061C}', "ARABIC LETTER MARK"),
+    ('\u{180E}', "MONGOLIAN VOWEL SEPARATOR"),
+    ('\u{200B}', "ZERO WIDTH SPACE"),
+    ('\u{200E}', "LEFT-TO-RIGHT MARK"),
+    ('\u{200F}', "RIGHT-TO-LEFT MARK"),
+    ('\u{202A}', "LEFT-TO-RIGHT EMBEDDING"),
+    ('\u{202B}', "RIGHT-TO-LEFT EMBEDDING"),
+    ('\u{202C}', "POP DIRECTIONAL FORMATTING"),
+    ('\u{202D}', "LEFT-TO-RIGHT OVERRIDE"),
+    ('\u{202E}', "RIGHT-TO-LEFT OVERRIDE"),
+    ('\u{2060}', "WORD JOINER"),
+    ('\u{2061}', "FUNCTION APPLICATION"),
+    ('\u{2062}', "INVISIBLE TIMES"),
+    ('\u{2063}', "INVISIBLE SEPARATOR"),
+    ('\u{2064}', "INVISIBLE PLUS"),
+    ('\u{2066}', "LEFT-TO-RIGHT ISOLATE"),
+    ('\u{2067}', "RIGHT-TO-LEFT ISOLATE"),
+    ('\u{2068}', "FIRST STRONG ISOLATE"),
+    ('\u{2069}', "POP DIRECTIONAL ISOLATE"),
+    ('\u{FEFF}', "ZERO WIDTH NO-BREAK SPACE"),
+];
+
+/// Shown past the last line for an insertion point at the end of the source
+pub(crate) const EOF_MARKER: &str = "<EOF>";
 
 #[derive(Debug)]
 pub(crate) struct SourceMap<'a> {
     lines: Vec<LineInfo<'a>>,
     pub(crate) source: &'a str,
+    width: Width,
 }
 
 impl<'a> SourceMap<'a> {
-    pub(crate) fn new(source: &'a str, line_start: usize) -> Self {
+    pub(crate) fn new(source: &'a str, line_start: usize, width: Width) -> Self {
         // Empty sources do have a "line", but it is empty, so we need to add
         // a line with an empty string to the source map.
         if source.is_empty() {
@@ -27,53 +62,53 @@ impl<'a> SourceMap<'a> {
                     end_line_size: 0,
                 }],
                 source,
+                width,
             };
         }
 
-        let mut current_index = 0;
-
         let mut mapping = vec![];
-        for (idx, (line, end_line)) in CursorLines::new(source).enumerate() {
-            let line_length = line.len();
-            let line_range = current_index..current_index + line_length;
-            let end_line_size = end_line.len();
+        push_lines(&mut mapping, source, line_start, 0);
+        Self {
+            lines: mapping,
+            source,
+            width,
+        }
+    }
 
-            mapping.push(LineInfo {
-                line,
-                line_index: line_start + idx,
-                start_byte: line_range.start,
-                end_byte: line_range.end + end_line_size,
-                end_line_size,
-            });
+    /// The lines of an [`Excerpt`][crate::source::Excerpt], where byte offsets are into the
+    /// whole source
+    pub(crate) fn with_chunks(source: &'a str, chunks: &[SourceChunk], width: Width) -> Self {
+        if chunks.is_empty() {
+            return Self::new(source, 1, width);
+        }
 
-            current_index += line_length + end_line_size;
+        let mut mapping = vec![];
+        let mut text_start = 0;
+        for chunk in chunks {
+            let text = &source[text_start..text_start + chunk.len];
+            push_lines(&mut mapping, text, chunk.line, chunk.offset);
+            text_start += chunk.len;
         }
         Self {
             lines: mapping,
             source,
+            width,
         }
     }
 
     pub(crate) fn get_line(&self, idx: usize) -> Option<&'a str> {
-        self.lines
-            .iter()
-            .find(|l| l.line_index == idx)
-            .map(|info| info.line)
+        self.line_info(idx).map(|info| info.line)
+    }
+
+    /// Whether the line `idx` ends with `\r\n`
+    pub(crate) fn is_crlf(&self, idx: usize) -> bool {
+        self.line_info(idx).is_some_and(LineInfo::is_crlf)
     }
 
     pub(crate) fn span_to_locations(&self, span: Range<usize>) -> (Loc, Loc) {
-        let start_info = self
-            .lines
-            .iter()
-            .find(|info| span.start >= info.start_byte && span.start < info.end_byte)
-            .unwrap_or(self.lines.last().unwrap());
-        let (mut start_char_pos, start_display_pos) = start_info.line
-            [0..(span.start - start_info.start_byte).min(start_info.line.len())]
-            .chars()
-            .fold((0, 0), |(char_pos, byte_pos), c| {
-                let display = char_width(c);
-                (char_pos + 1, byte_pos + display)
-            });
+        let start_info = self.line_info_at(span.start);
+        let (mut start_char_pos, start_display_pos) =
+            self.cluster_position(start_info.line, span.start - start_info.start_byte, false);
         // correct the char pos if we are highlighting the end of a line
         if (span.start - start_info.start_byte).saturating_sub(start_info.line.len()) > 0 {
             start_char_pos += 1;
@@ -89,18 +124,9 @@ impl<'a> SourceMap<'a> {
             return (start, start);
         }
 
-        let end_info = self
-            .lines
-            .iter()
-            .find(|info| span.end >= info.start_byte && span.end < info.end_byte)
-            .unwrap_or(self.lines.last().unwrap());
-        let (end_char_pos, end_display_pos) = end_info.line
-            [0..(span.end - end_info.start_byte).min(end_info.line.len())]
-            .chars()
-            .fold((0, 0), |(char_pos, byte_pos), c| {
-                let display = char_width(c);
-                (char_pos + 1, byte_pos + display)
-            });
+        let end_info = self.line_info_at(span.end);
+        let (end_char_pos, end_display_pos) =
+            self.cluster_position(end_info.line, span.end - end_info.start_byte, true);
 
         let mut end = Loc {
             line: end_info.line_index,
@@ -116,23 +142,33 @@ impl<'a> SourceMap<'a> {
         (start, end)
     }
 
+    /// The `char` count and display width of `line` up to byte `offset`
+    ///
+    /// When measuring by grapheme cluster, an `offset` inside a cluster snaps to the start of the
+    /// cluster, or to its end when `round_up`, so a cluster is never split by an underline.
+    fn cluster_position(&self, line: &str, offset: usize, round_up: bool) -> (usize, usize) {
+        let (mut char_pos, mut display_pos) = (0, 0);
+        for (start, cluster) in self.width.clusters(line) {
+            let end = start + cluster.len();
+            if offset <= start || (offset < end && !round_up) {
+                break;
+            }
+            char_pos += cluster.chars().count();
+            display_pos += cluster_width(cluster, self.width.ambiguous);
+        }
+        (char_pos, display_pos)
+    }
+
     pub(crate) fn span_to_snippet(&self, span: Range<usize>) -> Option<&str> {
         self.source.get(span)
     }
 
     pub(crate) fn span_to_lines(&self, span: Range<usize>) -> Vec<&LineInfo<'a>> {
-        let mut lines = vec![];
-        let start = span.start;
-        let end = span.end;
-        for line_info in &self.lines {
-            if start >= line_info.end_byte {
-                continue;
-            }
-            if end < line_info.start_byte {
-                break;
-            }
-            lines.push(line_info);
-        }
+        let first = self.lines.partition_point(|l| l.end_byte <= span.start);
+        let last = self.lines.partition_point(|l| l.start_byte <= span.end);
+        let mut lines = self.lines[first..max(first, last)]
+            .iter()
+            .collect::<Vec<_>>();
 
         if lines.is_empty() && !self.lines.is_empty() {
             lines.push(self.lines.last().unwrap());
@@ -143,10 +179,19 @@ impl<'a> SourceMap<'a> {
 
     pub(crate) fn annotated_lines(
         &self,
-        annotations: Vec<Annotation<'a>>,
-        fold: bool,
+        snippet: &Snippet<'a, Annotation<'a>>,
     ) -> (usize, Vec<AnnotatedLineInfo<'a>>) {
-        let source_len = self.source.len();
+        let mut annotations = snippet.markers.clone();
+        if snippet.reveal_invisible {
+            annotations.extend(self.invisible_annotations());
+        }
+        if !snippet.chunks.is_empty() {
+            // Excerpts can't show what wasn't read
+            annotations.retain(|ann| self.is_read(ann.span.start) && self.is_read(ann.span.end));
+        }
+        // Excerpts end with their final chunk, which is after their last line rather than at the
+        // end of their text
+        let source_len = self.lines.last().map_or(0, |line| line.end_byte);
         if let Some(bigger) = annotations.iter().find_map(|x| {
             // Allow highlighting one past the last character in the source.
             if source_len + 1 < x.span.end {
@@ -158,16 +203,8 @@ impl<'a> SourceMap<'a> {
             panic!("Annotation range `{bigger:?}` is beyond the end of buffer `{source_len}`")
         }
 
-        let mut annotated_line_infos = self
-            .lines
-            .iter()
-            .map(|info| AnnotatedLineInfo {
-                line: info.line,
-                line_index: info.line_index,
-                annotations: vec![],
-                keep: false,
-            })
-            .collect::<Vec<_>>();
+        // Only the lines with annotations, so the cost scales with them rather than the source
+        let mut annotated_line_infos = BTreeMap::new();
         let mut multiline_annotations = vec![];
 
         for Annotation {
@@ -175,9 +212,10 @@ impl<'a> SourceMap<'a> {
             label,
             kind,
             highlight_source,
+            insertion,
         } in annotations
         {
-            let (lo, mut hi) = self.span_to_locations(span.clone());
+            let (mut lo, mut hi) = self.span_to_locations(span.clone());
             if kind == AnnotationKind::Visible {
                 for line_idx in lo.line..=hi.line {
                     self.keep_line(&mut annotated_line_infos, line_idx);
@@ -190,7 +228,21 @@ impl<'a> SourceMap<'a> {
             // gracefully -- and the parser likes to supply a span like
             // that for EOF, in particular.
 
-            if lo.display == hi.display && lo.line == hi.line {
+            let mut annotation_type = LineAnnotationType::Singleline;
+            let mut eof = false;
+            if insertion && source_len <= span.start {
+                // Underline an `<EOF>` marker after the last line
+                let line = self.line_info(lo.line).map_or("", |info| info.line);
+                let (_, width) = self.cluster_position(line, line.len(), false);
+                lo.display = width + 1;
+                hi.display = lo.display + EOF_MARKER.len();
+                eof = true;
+            } else if insertion {
+                // Straddle the columns on both sides of the point
+                hi.display = lo.display + 1;
+                lo.display = lo.display.saturating_sub(1);
+                annotation_type = LineAnnotationType::Insertion;
+            } else if lo.display == hi.display && lo.line == hi.line {
                 hi.display += 1;
             }
 
@@ -200,10 +252,13 @@ impl<'a> SourceMap<'a> {
                     end: hi,
                     kind,
                     label,
-                    annotation_type: LineAnnotationType::Singleline,
+                    annotation_type,
                     highlight_source,
                 };
                 self.add_annotation_to_file(&mut annotated_line_infos, lo.line, line_ann);
+                if eof && let Some(line_info) = annotated_line_infos.get_mut(&lo.line) {
+                    line_info.eof = true;
+                }
             } else {
                 multiline_annotations.push(MultilineAnnotation {
                     depth: 1,
@@ -286,21 +341,15 @@ impl<'a> SourceMap<'a> {
                     ann.start.line,
                     ann.as_start(),
                 );
-                // 4 is the minimum vertical length of a multiline span when presented: two lines
-                // of code and two lines of underline. This is not true for the special case where
-                // the beginning doesn't have an underline, but the current logic seems to be
-                // working correctly.
-                let middle = min(ann.start.line + 4, ann.end.line);
-                // We'll show up to 4 lines past the beginning of the multispan start.
-                // We will *not* include the tail of lines that are only whitespace, a comment or
-                // a bare delimiter.
-                let filter = |s: &str| {
-                    let s = s.trim();
-                    // Consider comments as empty, but don't consider docstrings to be empty.
-                    !(s.starts_with("//") && !(s.starts_with("///") || s.starts_with("//!")))
-                        // Consider lines with nothing but whitespace, a single delimiter as empty.
-                        && !["", "{", "}", "(", ")", "[", "]"].contains(&s)
-                };
+                // 4, the default, is the minimum vertical length of a multiline span when
+                // presented: two lines of code and two lines of underline. This is not true for
+                // the special case where the beginning doesn't have an underline, but the current
+                // logic seems to be working correctly.
+                let middle = min(ann.start.line + snippet.multiline_lines, ann.end.line);
+                // We'll show up to `multiline_lines` lines past the beginning of the multispan
+                // start. We will *not* include the tail of lines that are insignificant in the
+                // snippet's language, like whitespace, a comment or a bare delimiter.
+                let filter = |s: &str| !snippet.language.is_insignificant(s);
                 let until = (ann.start.line..middle)
                     .rev()
                     .filter_map(|line| self.get_line(line).map(|s| (line + 1, s)))
@@ -319,60 +368,190 @@ impl<'a> SourceMap<'a> {
             self.add_annotation_to_file(&mut annotated_line_infos, end_ann.end.line, end_ann);
         }
 
-        if fold {
-            annotated_line_infos.retain(|l| !l.annotations.is_empty() || l.keep);
+        if !snippet.fold {
+            let annotated_line_infos = self
+                .lines
+                .iter()
+                .map(|info| {
+                    annotated_line_infos
+                        .remove(&info.line_index)
+                        .unwrap_or(AnnotatedLineInfo {
+                            line: info.line,
+                            line_index: info.line_index,
+                            annotations: vec![],
+                            keep: false,
+                            crlf: info.is_crlf(),
+                            eof: false,
+                        })
+                })
+                .collect();
+            return (max_depth, annotated_line_infos);
+        }
+
+        let (context_before, context_after) = snippet.context_lines;
+        if context_before > 0 || context_after > 0 {
+            // Keep the lines around each shown line, like `grep --context`
+            let first = self.lines.first().map_or(0, |l| l.line_index);
+            let last = self.lines.last().map_or(0, |l| l.line_index);
+            let shown = annotated_line_infos.keys().copied().collect::<Vec<_>>();
+            for line_index in shown {
+                let start = max(line_index.saturating_sub(context_before), first);
+                let end = min(line_index.saturating_add(context_after), last);
+                for context in start..=end {
+                    self.keep_line(&mut annotated_line_infos, context);
+                }
+            }
         }
 
-        (max_depth, annotated_line_infos)
+        (max_depth, annotated_line_infos.into_values().collect())
     }
 
     fn add_annotation_to_file(
         &self,
-        annotated_line_infos: &mut Vec<AnnotatedLineInfo<'a>>,
+        annotated_line_infos: &mut BTreeMap<usize, AnnotatedLineInfo<'a>>,
         line_index: usize,
         line_ann: LineAnnotation<'a>,
     ) {
-        if let Some(line_info) = annotated_line_infos
-            .iter_mut()
-            .find(|line_info| line_info.line_index == line_index)
-        {
-            line_info.annotations.push(line_ann);
-        } else {
-            let info = self
-                .lines
-                .iter()
-                .find(|l| l.line_index == line_index)
-                .unwrap();
-            annotated_line_infos.push(AnnotatedLineInfo {
+        // Multiline annotations can span lines an excerpt skipped
+        let Some(info) = self.line_info(line_index) else {
+            return;
+        };
+        annotated_line_infos
+            .entry(line_index)
+            .or_insert_with(|| AnnotatedLineInfo {
                 line: info.line,
                 line_index,
-                annotations: vec![line_ann],
+                annotations: vec![],
                 keep: false,
-            });
-            annotated_line_infos.sort_by_key(|l| l.line_index);
+                crlf: info.is_crlf(),
+                eof: false,
+            })
+            .annotations
+            .push(line_ann);
+    }
+
+    /// A [`AnnotationKind::Context`] annotation naming each of the [`INVISIBLE_CHARS`]
+    fn invisible_annotations(&self) -> impl Iterator<Item = Annotation<'a>> + '_ {
+        self.lines.iter().flat_map(|info| {
+            info.line.char_indices().filter_map(|(i, c)| {
+                let name = INVISIBLE_CHARS
+                    .binary_search_by_key(&c, |(k, _)| *k)
+                    .ok()
+                    .map(|i| INVISIBLE_CHARS[i].1)?;
+                let start = info.start_byte + i;
+                let label = format!("U+{:04X} {name}", u32::from(c));
+                Some(
+                    AnnotationKind::Context
+                        .span(start..start + c.len_utf8())
+                        .label(label),
+                )
+            })
+        })
+    }
+
+    /// Show the line, if it is in the source, even without annotations
+    fn keep_line(
+        &self,
+        annotated_line_infos: &mut BTreeMap<usize, AnnotatedLineInfo<'a>>,
+        line_index: usize,
+    ) {
+        if let Some(info) = self.line_info(line_index) {
+            annotated_line_infos
+                .entry(line_index)
+                .or_insert_with(|| AnnotatedLineInfo {
+                    line: info.line,
+                    line_index,
+                    annotations: vec![],
+                    keep: false,
+                    crlf: info.is_crlf(),
+                    eof: false,
+                })
+                .keep = true;
         }
     }
 
-    fn keep_line(&self, annotated_line_infos: &mut Vec<AnnotatedLineInfo<'a>>, line_index: usize) {
-        if let Some(line_info) = annotated_line_infos
-            .iter_mut()
-            .find(|line_info| line_info.line_index == line_index)
-        {
-            line_info.keep = true;
-        } else {
-            let info = self
-                .lines
-                .iter()
-                .find(|l| l.line_index == line_index)
-                .unwrap();
-            annotated_line_infos.push(AnnotatedLineInfo {
-                line: info.line,
-                line_index,
-                annotations: vec![],
-                keep: true,
+    /// Whether `byte` is in, or right after, a line of the source map
+    fn is_read(&self, byte: usize) -> bool {
+        let info = self.line_info_at(byte);
+        info.start_byte <= byte && byte <= info.end_byte
+    }
+
+    /// The [`LineInfo`] for the (1-based) line number `line_index`
+    fn line_info(&self, line_index: usize) -> Option<&LineInfo<'a>> {
+        self.lines
+            .binary_search_by_key(&line_index, |l| l.line_index)
+            .ok()
+            .map(|i| &self.lines[i])
+    }
+
+    /// The [`LineInfo`] for the line containing `byte`, or the last line when past the end
+    fn line_info_at(&self, byte: usize) -> &LineInfo<'a> {
+        // `lines` are sorted by their start, and for excerpts there can be gaps between them
+        let after = self.lines.partition_point(|info| info.start_byte <= byte);
+        after
+            .checked_sub(1)
+            .map(|i| &self.lines[i])
+            .filter(|info| byte < info.end_byte)
+            .unwrap_or(self.lines.last().unwrap())
+    }
+
+    /// Apply `patches`, grouping them by the whole lines they touch
+    ///
+    /// Patches touching overlapping lines end up in the same [`PatchedHunk`].
+    pub(crate) fn patched_hunks(&self, patches: &[Patch<'_>]) -> Vec<PatchedHunk<'a>> {
+        let source_len = self.source.len();
+        let mut patches = patches.iter().collect::<Vec<_>>();
+        patches.sort_by_key(|p| (p.span.start, p.span.end));
+
+        let mut hunks: Vec<(Range<usize>, usize, Vec<&Patch<'_>>)> = vec![];
+        for patch in patches {
+            let start = min(patch.span.start, source_len);
+            let end = min(patch.span.end, source_len);
+            let first = self.line_info_at(start);
+            let last = self.line_info_at(max(start, end.saturating_sub(1)));
+            let range = first.start_byte..last.end_byte;
+            match hunks.last_mut() {
+                Some((hunk_range, _, hunk_patches)) if range.start < hunk_range.end => {
+                    hunk_range.end = max(hunk_range.end, range.end);
+                    hunk_patches.push(patch);
+                }
+                _ => hunks.push((range, first.line_index, vec![patch])),
+            }
+        }
+
+        let apply = |range: &Range<usize>, patches: &[&Patch<'_>]| {
+            splice(
+                self.source,
+                range.clone(),
+                patches
+                    .iter()
+                    .map(|patch| (patch.span.clone(), &*patch.replacement)),
+            )
+        };
+
+        let mut patched_hunks = vec![];
+        let mut hunks = hunks.into_iter().peekable();
+        while let Some((mut range, line, mut patches)) = hunks.next() {
+            let mut patched = apply(&range, &patches);
+            // When the last line ending is removed, the next line joins the
+            // patched lines
+            while !patched.is_empty() && !patched.ends_with('\n') && range.end < source_len {
+                range.end = self.line_info_at(range.end).end_byte;
+                while let Some((next_range, _, next_patches)) =
+                    hunks.next_if(|(next_range, _, _)| next_range.start < range.end)
+                {
+                    range.end = max(range.end, next_range.end);
+                    patches.extend(next_patches);
+                }
+                patched = apply(&range, &patches);
+            }
+            patched_hunks.push(PatchedHunk {
+                line,
+                original: &self.source[range],
+                patched,
             });
-            annotated_line_infos.sort_by_key(|l| l.line_index);
         }
+        patched_hunks
     }
 
     pub(crate) fn splice_lines<'b>(
@@ -380,29 +559,6 @@ impl<'a> SourceMap<'a> {
         mut patches: Vec<Patch<'b>>,
         fold: bool,
     ) -> Option<SplicedLines<'b>> {
-        fn push_trailing(buf: &mut String, line_opt: Option<&str>, lo: &Loc, hi_opt: Option<&Loc>) {
-            // Convert CharPos to Usize, as CharPose is character offset
-            // Extract low index and high index
-            let (lo, hi_opt) = (lo.char, hi_opt.map(|hi| hi.char));
-            if let Some(line) = line_opt {
-                if let Some(lo) = line.char_indices().map(|(i, _)| i).nth(lo) {
-                    // Get high index while account for rare unicode and emoji with char_indices
-                    let hi_opt = hi_opt.and_then(|hi| line.char_indices().map(|(i, _)| i).nth(hi));
-                    match hi_opt {
-                        // If high index exist, take string from low to high index
-                        Some(hi) if hi > lo => buf.push_str(&line[lo..hi]),
-                        Some(_) => (),
-                        // If high index absence, take string from low index till end string.len
-                        None => buf.push_str(&line[lo..]),
-                    }
-                }
-                // If high index is None
-                if hi_opt.is_none() {
-                    buf.push('\n');
-                }
-            }
-        }
-
         let source_len = self.source.len();
         if let Some(bigger) = patches.iter().find_map(|x| {
             // Allow patching one past the last character in the source.
@@ -443,8 +599,6 @@ impl<'a> SourceMap<'a> {
         // Finally push the trailing line segment of the last span
         let mut prev_hi = bounding_lo;
         prev_hi.char = 0;
-        let mut prev_line = lines.first().map(|line| line.line);
-        let mut buf = String::new();
 
         let trimmed_patches = patches
             .into_iter()
@@ -459,32 +613,18 @@ impl<'a> SourceMap<'a> {
         let mut acc = 0;
         for part in &trimmed_patches {
             let (cur_lo, cur_hi) = self.span_to_locations(part.span.clone());
-            if prev_hi.line == cur_lo.line {
-                push_trailing(&mut buf, prev_line, &prev_hi, Some(&cur_lo));
-            } else {
+            if prev_hi.line != cur_lo.line {
                 acc = 0;
                 highlights.push(core::mem::take(&mut line_highlight));
-                push_trailing(&mut buf, prev_line, &prev_hi, None);
-                // push lines between the previous and current span (if any)
+                // lines between the previous and current span (if any)
                 for idx in prev_hi.line + 1..(cur_lo.line) {
-                    if let Some(line) = self.get_line(idx) {
-                        buf.push_str(line.as_ref());
-                        buf.push('\n');
+                    if self.get_line(idx).is_some() {
                         highlights.push(core::mem::take(&mut line_highlight));
                     }
                 }
-                if let Some(cur_line) = self.get_line(cur_lo.line) {
-                    let end = match cur_line.char_indices().nth(cur_lo.char) {
-                        Some((i, _)) => i,
-                        None => cur_line.len(),
-                    };
-                    buf.push_str(&cur_line[..end]);
-                }
             }
             // Add a whole line highlight per line in the snippet.
-            let len: isize = part
-                .replacement
-                .split('\n')
+            let len: isize = replacement_lines(&part.replacement)
                 .next()
                 .unwrap_or(&part.replacement)
                 .chars()
@@ -497,15 +637,13 @@ impl<'a> SourceMap<'a> {
                 start: (cur_lo.char.cast_signed() + acc).cast_unsigned(),
                 end: (cur_lo.char.cast_signed() + acc + len).cast_unsigned(),
             });
-            buf.push_str(&part.replacement);
             // Account for the difference between the width of the current code and the
             // snippet being suggested, so that the *later* suggestions are correctly
             // aligned on the screen. Note that cur_hi and cur_lo can be on different
             // lines, so cur_hi.col can be smaller than cur_lo.col
             acc += len - (cur_hi.char.cast_signed() - cur_lo.char.cast_signed());
             prev_hi = cur_hi;
-            prev_line = self.get_line(prev_hi.line);
-            for line in part.replacement.split('\n').skip(1) {
+            for line in replacement_lines(&part.replacement).skip(1) {
                 acc = 0;
                 highlights.push(core::mem::take(&mut line_highlight));
                 let end: usize = line
@@ -519,23 +657,43 @@ impl<'a> SourceMap<'a> {
             }
         }
         highlights.push(core::mem::take(&mut line_highlight));
-        if fold {
+        let start = lines.first().map_or(0, |line| line.start_byte);
+        // Like the highlights, show spans past the end of a line at its end
+        let clamp = |byte: usize| {
+            let info = self.line_info_at(byte);
+            min(byte, info.start_byte + info.line.len())
+        };
+        let patches = trimmed_patches.iter().map(|part| {
+            (
+                clamp(part.span.start)..clamp(part.span.end),
+                &*part.replacement,
+            )
+        });
+        let mut buf = if fold {
+            let end = trimmed_patches
+                .iter()
+                .map(|part| clamp(part.span.end))
+                .max()
+                .unwrap_or(start);
+            let mut buf = splice(self.source, start..end, patches);
             // if the replacement already ends with a newline, don't print the next line
             if !buf.ends_with('\n') {
-                push_trailing(&mut buf, prev_line, &prev_hi, None);
+                buf.push_str(&self.source[end..self.line_info_at(end).end_byte.max(end)]);
             }
+            buf
         } else {
             // Add the trailing part of the source after the last patch
             if let Some(snippet) = self.span_to_snippet(prev_hi.byte..source_len) {
-                buf.push_str(snippet);
                 for _ in snippet.matches('\n') {
                     highlights.push(core::mem::take(&mut line_highlight));
                 }
             }
-        }
+            splice(self.source, start..source_len, patches)
+        };
         // remove trailing newlines
-        while buf.ends_with('\n') {
-            buf.pop();
+        while let Some(rest) = buf.strip_suffix('\n') {
+            let len = rest.strip_suffix('\r').unwrap_or(rest).len();
+            buf.truncate(len);
         }
 
         let line_count = bounding_hi.line.saturating_sub(bounding_lo.line) + 1;
@@ -641,12 +799,22 @@ pub(crate) struct LineInfo<'a> {
     end_line_size: usize,
 }
 
+impl LineInfo<'_> {
+    fn is_crlf(&self) -> bool {
+        self.end_line_size == 2
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct AnnotatedLineInfo<'a> {
     pub(crate) line: &'a str,
     pub(crate) line_index: usize,
     pub(crate) annotations: Vec<LineAnnotation<'a>>,
     pub(crate) keep: bool,
+    /// Whether the line ends with `\r\n`
+    pub(crate) crlf: bool,
+    /// Whether the line is followed by an [`EOF_MARKER`]
+    pub(crate) eof: bool,
 }
 
 /// A source code location used for error reporting.
@@ -662,6 +830,60 @@ pub(crate) struct Loc {
     pub(crate) byte: usize,
 }
 
+/// Add the lines of `text`, which starts at line `line_start` and byte `byte_start`
+fn push_lines<'a>(
+    mapping: &mut Vec<LineInfo<'a>>,
+    text: &'a str,
+    line_start: usize,
+    byte_start: usize,
+) {
+    let mut current_index = byte_start;
+    for (idx, (line, end_line)) in CursorLines::new(text).enumerate() {
+        let line_length = line.len();
+        let line_range = current_index..current_index + line_length;
+        let end_line_size = end_line.len();
+
+        mapping.push(LineInfo {
+            line,
+            line_index: line_start + idx,
+            start_byte: line_range.start,
+            end_byte: line_range.end + end_line_size,
+            end_line_size,
+        });
+
+        current_index += line_length + end_line_size;
+    }
+}
+
+/// Apply `patches`, sorted by their start, to `source[range]`
+///
+/// Spans are clamped to the source, and a patch overlapping earlier ones only replaces what
+/// follows them.
+pub(crate) fn splice<'r>(
+    source: &str,
+    range: Range<usize>,
+    patches: impl IntoIterator<Item = (Range<usize>, &'r str)>,
+) -> String {
+    let mut patched = String::new();
+    let mut pos = range.start;
+    for (span, replacement) in patches {
+        let start = min(span.start, source.len());
+        patched.push_str(&source[pos..max(pos, start)]);
+        patched.push_str(replacement);
+        pos = max(pos, min(span.end, source.len()));
+    }
+    patched.push_str(&source[min(pos, range.end)..range.end]);
+    patched
+}
+
+/// The lines of a [`Patch`]'s replacement, split at `\n` like [`str::split`], without the `\r`
+/// of a `\r\n`
+fn replacement_lines(replacement: &str) -> impl Iterator<Item = &str> {
+    replacement
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+}
+
 struct CursorLines<'a>(&'a str);
 
 impl CursorLines<'_> {
@@ -729,6 +951,17 @@ pub(crate) struct SplicedLines<'a> {
     pub(crate) replaced_highlights: Vec<Vec<SubstitutionHighlight>>,
 }
 
+/// Whole lines of source before and after applying [`Patch`]es
+#[derive(Clone, Debug)]
+pub(crate) struct PatchedHunk<'a> {
+    /// The (1-based) line number of the first line in `original`
+    pub(crate) line: usize,
+    /// The original lines, including their line endings
+    pub(crate) original: &'a str,
+    /// `original` with the patches applied
+    pub(crate) patched: String,
+}
+
 /// Used to translate between `Span`s and byte positions within a single output line in highlighted
 /// code of structured suggestions.
 #[derive(Debug, Clone, Copy)]
@@ -6,8 +6,11 @@ use alloc::string::String;
 use alloc::{vec, vec::Vec};
 use core::fmt::{self, Write};
 
+use anstyle::Style;
+
 use crate::Level;
 use crate::renderer::ElementStyle;
+use crate::renderer::render::{Width, str_width};
 use crate::renderer::stylesheet::Stylesheet;
 
 #[derive(Debug)]
@@ -70,6 +73,36 @@ impl StyledBuffer {
         Ok(())
     }
 
+    /// Each line as runs of text that share the same resolved [`Style`]
+    pub(crate) fn styled_lines(
+        &self,
+        level: &Level<'_>,
+        stylesheet: &Stylesheet,
+    ) -> Vec<Vec<(Style, String)>> {
+        self.runs(|style| style.color_spec(level, stylesheet))
+    }
+
+    /// Each line as runs of text that `resolve` their [`ElementStyle`] to the same value
+    pub(crate) fn runs<S: PartialEq>(
+        &self,
+        resolve: impl Fn(ElementStyle) -> S,
+    ) -> Vec<Vec<(S, String)>> {
+        self.lines
+            .iter()
+            .map(|line| {
+                let mut runs: Vec<(S, String)> = Vec::new();
+                for StyledChar { ch, style } in line {
+                    let ch_style = resolve(*style);
+                    match runs.last_mut() {
+                        Some((style, text)) if *style == ch_style => text.push(*ch),
+                        _ => runs.push((ch_style, String::from(*ch))),
+                    }
+                }
+                runs
+            })
+            .collect()
+    }
+
     /// Sets `chr` with `style` for given `line`, `col`.
     /// If `line` does not exist in our buffer, adds empty lines up to the given
     /// and fills the last line with unstyled whitespace.
@@ -136,6 +169,20 @@ impl StyledBuffer {
         self.lines.len()
     }
 
+    /// The display width of the widest line
+    ///
+    /// ANSI escape codes in the text, e.g. of a [`Level::secondary_title`], take no columns.
+    pub(crate) fn max_width(&self, width: Width) -> usize {
+        self.lines
+            .iter()
+            .map(|line| {
+                let line = strip_escapes(line.iter().map(|c| c.ch));
+                str_width(&line, width)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
     /// Set `style` for `line`, `col_start..col_end` range if:
     /// 1. That line and column range exist in `StyledBuffer`
     /// 2. `overwrite` is `true` or existing style is `Style::NoStyle` or `Style::Quotation`
@@ -170,3 +217,39 @@ impl StyledBuffer {
         }
     }
 }
+
+/// Drop CSI sequences, e.g. `\x1b[1m`, and OSC sequences, e.g. `\x1b]8;;url\x1b\\` hyperlinks
+fn strip_escapes(chars: impl Iterator<Item = char>) -> String {
+    let mut out = String::new();
+    let mut chars = chars.peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // Parameters and intermediates end at a final byte in `@..=~`
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // Terminated by BEL or ST (`\x1b\\`)
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' {
+                        chars.next_if_eq(&'\\');
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
@@ -0,0 +1,595 @@
+//! Render a [`Report`] as an SVG image of a terminal
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+
+use anstyle::{Ansi256Color, AnsiColor, Color, Effects, RgbColor, Style};
+
+use super::Renderer;
+use super::render::{render_buffers, str_width};
+use crate::Report;
+
+/// Terminal-like settings for [`Renderer::render_svg`]
+///
+/// The defaults match the SVG snapshots used in this repository: light grey text on a black
+/// background with the [`Palette::VGA`] colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Svg {
+    palette: Palette,
+    fg_color: Color,
+    bg_color: Color,
+    background: bool,
+    font_family: &'static str,
+    font_size_px: usize,
+    line_height_px: usize,
+    min_width_px: usize,
+    padding_px: usize,
+}
+
+impl Svg {
+    /// Default terminal settings
+    pub const fn new() -> Self {
+        Self {
+            palette: Palette::VGA,
+            fg_color: Color::Ansi(AnsiColor::White),
+            bg_color: Color::Ansi(AnsiColor::Black),
+            background: true,
+            font_family: "SFMono-Regular, Consolas, Liberation Mono, Menlo, monospace",
+            font_size_px: 14,
+            line_height_px: 18,
+            min_width_px: 720,
+            padding_px: 10,
+        }
+    }
+
+    /// Select the RGB values for [`AnsiColor`]s
+    pub const fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Select the default foreground color
+    pub const fn fg_color(mut self, color: Color) -> Self {
+        self.fg_color = color;
+        self
+    }
+
+    /// Select the default background color
+    pub const fn bg_color(mut self, color: Color) -> Self {
+        self.bg_color = color;
+        self
+    }
+
+    /// Toggle the default background off with `false`, e.g. for embedding in a page
+    pub const fn background(mut self, yes: bool) -> Self {
+        self.background = yes;
+        self
+    }
+
+    /// Select the CSS `font-family`, which should be monospace
+    pub const fn font_family(mut self, font_family: &'static str) -> Self {
+        self.font_family = font_family;
+        self
+    }
+
+    /// Select the font size
+    ///
+    /// Columns are assumed to be `0.6` times as wide as the font size, as is typical for
+    /// monospace fonts.
+    pub const fn font_size_px(mut self, px: usize) -> Self {
+        self.font_size_px = px;
+        self
+    }
+
+    /// Select the distance between lines
+    pub const fn line_height_px(mut self, px: usize) -> Self {
+        self.line_height_px = px;
+        self
+    }
+
+    /// Minimum width for the text
+    pub const fn min_width_px(mut self, px: usize) -> Self {
+        self.min_width_px = px;
+        self
+    }
+
+    /// Space between the text and the edge of the image
+    pub const fn padding_px(mut self, px: usize) -> Self {
+        self.padding_px = px;
+        self
+    }
+}
+
+impl Default for Svg {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// RGB values for the 16 [`AnsiColor`]s, in [`AnsiColor`] order
+///
+/// Terminals let users pick these, so an image has to commit to one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette(pub [RgbColor; 16]);
+
+impl Palette {
+    /// Typical colors that are used when booting PCs and leaving them in text mode
+    pub const VGA: Self = Self([
+        RgbColor(0, 0, 0),
+        RgbColor(170, 0, 0),
+        RgbColor(0, 170, 0),
+        RgbColor(170, 85, 0),
+        RgbColor(0, 0, 170),
+        RgbColor(170, 0, 170),
+        RgbColor(0, 170, 170),
+        RgbColor(170, 170, 170),
+        RgbColor(85, 85, 85),
+        RgbColor(255, 85, 85),
+        RgbColor(85, 255, 85),
+        RgbColor(255, 255, 85),
+        RgbColor(85, 85, 255),
+        RgbColor(255, 85, 255),
+        RgbColor(85, 255, 255),
+        RgbColor(255, 255, 255),
+    ]);
+
+    /// Campbell theme, used by the Windows 10 console
+    pub const WIN10_CONSOLE: Self = Self([
+        RgbColor(12, 12, 12),
+        RgbColor(197, 15, 31),
+        RgbColor(19, 161, 14),
+        RgbColor(193, 156, 0),
+        RgbColor(0, 55, 218),
+        RgbColor(136, 23, 152),
+        RgbColor(58, 150, 221),
+        RgbColor(204, 204, 204),
+        RgbColor(118, 118, 118),
+        RgbColor(231, 72, 86),
+        RgbColor(22, 198, 12),
+        RgbColor(249, 241, 165),
+        RgbColor(59, 120, 255),
+        RgbColor(180, 0, 158),
+        RgbColor(97, 214, 214),
+        RgbColor(242, 242, 242),
+    ]);
+
+    /// Resolve `color` to RGB, using the xterm values beyond the first 16
+    /// [`Ansi256Color`]s
+    pub const fn rgb(&self, color: Color) -> RgbColor {
+        match color {
+            Color::Ansi(color) => self.0[color as usize],
+            Color::Ansi256(Ansi256Color(index)) => {
+                if index < 16 {
+                    self.0[index as usize]
+                } else if index < 232 {
+                    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+                    let index = index - 16;
+                    RgbColor(
+                        LEVELS[(index / 36) as usize],
+                        LEVELS[(index / 6 % 6) as usize],
+                        LEVELS[(index % 6) as usize],
+                    )
+                } else {
+                    let grey = 8 + 10 * (index - 232);
+                    RgbColor(grey, grey, grey)
+                }
+            }
+            Color::Rgb(color) => color,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::VGA
+    }
+}
+
+/// CSS classes for [`Effects`], in the order they are listed on a `<tspan>`
+const EFFECTS: [(Effects, &str, &str); 10] = [
+    (
+        Effects::UNDERLINE,
+        "underline",
+        "text-decoration-line: underline;",
+    ),
+    (
+        Effects::DOUBLE_UNDERLINE,
+        "double-underline",
+        "text-decoration-line: underline; text-decoration-style: double;",
+    ),
+    (
+        Effects::CURLY_UNDERLINE,
+        "curly-underline",
+        "text-decoration-line: underline; text-decoration-style: wavy;",
+    ),
+    (
+        Effects::DOTTED_UNDERLINE,
+        "dotted-underline",
+        "text-decoration-line: underline; text-decoration-style: dotted;",
+    ),
+    (
+        Effects::DASHED_UNDERLINE,
+        "dashed-underline",
+        "text-decoration-line: underline; text-decoration-style: dashed;",
+    ),
+    (
+        Effects::STRIKETHROUGH,
+        "strikethrough",
+        "text-decoration-line: line-through;",
+    ),
+    (Effects::BOLD, "bold", "font-weight: bold;"),
+    (Effects::ITALIC, "italic", "font-style: italic;"),
+    (Effects::DIMMED, "dimmed", "opacity: 0.4;"),
+    (Effects::HIDDEN, "hidden", "opacity: 0;"),
+];
+
+pub(crate) fn render_svg(renderer: &Renderer, groups: Report<'_>) -> String {
+    let svg = &renderer.svg;
+    // Escape codes would end up as text in the image
+    let renderer = Renderer {
+        hyperlink: false,
+        ..renderer.clone()
+    };
+
+    let stylesheet = renderer.stylesheet.downgrade(renderer.color_support);
+
+    let mut lines: Vec<Vec<(Style, String)>> = Vec::new();
+    for (level, buffer) in render_buffers(&renderer, groups) {
+        let group_lines = buffer.styled_lines(&level, &stylesheet);
+        if group_lines.is_empty() {
+            lines.push(Vec::new());
+        }
+        lines.extend(group_lines.iter().map(|line| apply_escapes(line)));
+    }
+    for line in &mut lines {
+        for (style, _) in line.iter_mut() {
+            if style.get_effects().contains(Effects::INVERT) {
+                *style = style
+                    .fg_color(Some(style.get_bg_color().unwrap_or(svg.bg_color)))
+                    .bg_color(Some(style.get_fg_color().unwrap_or(svg.fg_color)))
+                    .effects(style.get_effects().remove(Effects::INVERT));
+            }
+        }
+    }
+
+    let line_height = svg.line_height_px;
+    let padding = svg.padding_px;
+    let height = lines.len() * line_height + padding * 2;
+    let max_width = lines
+        .iter()
+        .map(|line| {
+            line.iter()
+                .map(|(_, text)| str_width(text, renderer.width()))
+                .sum::<usize>()
+        })
+        .max()
+        .unwrap_or(0);
+    // Monospace glyphs are typically 0.6em wide
+    let width = (max_width * svg.font_size_px * 6).div_ceil(10);
+    let width = width.max(svg.min_width_px) + padding * 2;
+
+    let mut colors = BTreeMap::new();
+    let mut effects_in_use = Effects::new();
+    for (style, _) in lines.iter().flatten() {
+        if let Some(color) = style.get_fg_color() {
+            colors.insert(color_class("fg", color), svg.palette.rgb(color));
+        }
+        if let Some(color) = style.get_bg_color() {
+            colors.insert(color_class("bg", color), svg.palette.rgb(color));
+        }
+        if let Some(color) = style.get_underline_color() {
+            colors.insert(color_class("underline", color), svg.palette.rgb(color));
+        }
+        effects_in_use |= style.get_effects();
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg width="{width}px" height="{height}px" xmlns="http://www.w3.org/2000/svg">"#
+    )
+    .unwrap();
+    writeln!(out, "  <style>").unwrap();
+    writeln!(
+        out,
+        "    .fg {{ fill: {} }}",
+        hex(svg.palette.rgb(svg.fg_color))
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    .bg {{ fill: {} }}",
+        hex(svg.palette.rgb(svg.bg_color))
+    )
+    .unwrap();
+    for (class, rgb) in &colors {
+        let rgb = hex(*rgb);
+        if class.starts_with("fg") {
+            writeln!(out, "    .{class} {{ fill: {rgb} }}").unwrap();
+        } else if class.starts_with("bg") {
+            writeln!(
+                out,
+                "    .{class} {{ stroke: {rgb}; fill: {rgb}; user-select: none;  }}"
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                out,
+                "    .{class} {{ text-decoration-line: underline; text-decoration-color: {rgb} }}"
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "    .container {{").unwrap();
+    writeln!(out, "      padding: 0 {padding}px;").unwrap();
+    writeln!(out, "      line-height: {line_height}px;").unwrap();
+    writeln!(out, "    }}").unwrap();
+    for (effect, class, css) in EFFECTS {
+        if effects_in_use.contains(effect) {
+            writeln!(out, "    .{class} {{ {css} }}").unwrap();
+        }
+    }
+    writeln!(out, "    tspan {{").unwrap();
+    writeln!(
+        out,
+        "      font: {}px {};",
+        svg.font_size_px, svg.font_family
+    )
+    .unwrap();
+    writeln!(out, "      white-space: pre;").unwrap();
+    writeln!(out, "      line-height: {line_height}px;").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "  </style>").unwrap();
+    writeln!(out).unwrap();
+
+    if svg.background {
+        writeln!(
+            out,
+            r#"  <rect width="100%" height="100%" y="0" rx="4.5" class="bg" />"#
+        )
+        .unwrap();
+        writeln!(out).unwrap();
+    }
+
+    writeln!(out, r#"  <text xml:space="preserve" class="container fg">"#).unwrap();
+    let mut y = padding + line_height;
+    for line in &lines {
+        if line.iter().any(|(style, _)| style.get_bg_color().is_some()) {
+            write!(out, r#"    <tspan x="{padding}px" y="{y}px">"#).unwrap();
+            for (style, text) in line {
+                // Backgrounds are drawn as a row of full blocks behind the text
+                let (class, fill) = match style.get_bg_color() {
+                    Some(color) => (Some(color_class("bg", color)), "█"),
+                    None => (None, " "),
+                };
+                write_tspan(
+                    &mut out,
+                    class.as_deref(),
+                    &fill.repeat(str_width(text, renderer.width())),
+                );
+            }
+            // Closing on the next line keeps the newline when copying the text
+            writeln!(out).unwrap();
+            writeln!(out, "</tspan>").unwrap();
+        }
+
+        write!(out, r#"    <tspan x="{padding}px" y="{y}px">"#).unwrap();
+        for (style, text) in line {
+            let mut classes = Vec::new();
+            if let Some(color) = style.get_fg_color() {
+                classes.push(Cow::Owned(color_class("fg", color)));
+            }
+            if let Some(color) = style.get_underline_color() {
+                classes.push(Cow::Owned(color_class("underline", color)));
+            }
+            for (effect, class, _) in EFFECTS {
+                if style.get_effects().contains(effect) {
+                    classes.push(Cow::Borrowed(class));
+                }
+            }
+            let classes = (!classes.is_empty()).then(|| classes.join(" "));
+            write_tspan(&mut out, classes.as_deref(), &escape(text));
+        }
+        writeln!(out).unwrap();
+        writeln!(out, "</tspan>").unwrap();
+
+        y += line_height;
+    }
+    writeln!(out, "  </text>").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+/// Apply escape codes in text that [allows styling][crate::Title] on top of the
+/// style of its run, as a terminal would
+fn apply_escapes(line: &[(Style, String)]) -> Vec<(Style, String)> {
+    let mut runs: Vec<(Style, String)> = Vec::new();
+    for (run_style, text) in line {
+        let mut style = *run_style;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\x1b' {
+                match runs.last_mut() {
+                    Some((last, text)) if *last == style => text.push(c),
+                    _ => runs.push((style, String::from(c))),
+                }
+                continue;
+            }
+            match chars.next() {
+                // CSI: parameters, then a final byte
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut action = None;
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            action = Some(c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                    if action == Some('m') {
+                        style = select_graphic_rendition(style, &params);
+                    }
+                }
+                // OSC, e.g. hyperlinks: skip to the string terminator
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    runs
+}
+
+fn select_graphic_rendition(mut style: Style, params: &str) -> Style {
+    const UNDERLINES: Effects = Effects::UNDERLINE
+        .insert(Effects::DOUBLE_UNDERLINE)
+        .insert(Effects::CURLY_UNDERLINE)
+        .insert(Effects::DOTTED_UNDERLINE)
+        .insert(Effects::DASHED_UNDERLINE);
+
+    let mut params = params.split(';');
+    while let Some(param) = params.next() {
+        let mut sub = param.split(':');
+        let code = sub
+            .next()
+            .and_then(|code| code.parse::<u8>().ok())
+            .unwrap_or(0);
+        let effects = style.get_effects();
+        style = match code {
+            0 => Style::new(),
+            1 => style.effects(effects | Effects::BOLD),
+            2 => style.effects(effects | Effects::DIMMED),
+            3 => style.effects(effects | Effects::ITALIC),
+            4 => {
+                let underline = match sub.next() {
+                    Some("0") => Effects::new(),
+                    Some("2") => Effects::DOUBLE_UNDERLINE,
+                    Some("3") => Effects::CURLY_UNDERLINE,
+                    Some("4") => Effects::DOTTED_UNDERLINE,
+                    Some("5") => Effects::DASHED_UNDERLINE,
+                    _ => Effects::UNDERLINE,
+                };
+                style.effects(effects.remove(UNDERLINES) | underline)
+            }
+            7 => style.effects(effects | Effects::INVERT),
+            8 => style.effects(effects | Effects::HIDDEN),
+            9 => style.effects(effects | Effects::STRIKETHROUGH),
+            21 => style.effects(effects | Effects::DOUBLE_UNDERLINE),
+            22 => style.effects(effects.remove(Effects::BOLD).remove(Effects::DIMMED)),
+            23 => style.effects(effects.remove(Effects::ITALIC)),
+            24 => style.effects(effects.remove(UNDERLINES)),
+            27 => style.effects(effects.remove(Effects::INVERT)),
+            28 => style.effects(effects.remove(Effects::HIDDEN)),
+            29 => style.effects(effects.remove(Effects::STRIKETHROUGH)),
+            30..=37 => style.fg_color(ansi_color(code - 30, false)),
+            39 => style.fg_color(None),
+            40..=47 => style.bg_color(ansi_color(code - 40, false)),
+            49 => style.bg_color(None),
+            59 => style.underline_color(None),
+            90..=97 => style.fg_color(ansi_color(code - 90, true)),
+            100..=107 => style.bg_color(ansi_color(code - 100, true)),
+            38 | 48 | 58 => {
+                // The color is either in `:` sub-parameters or the following parameters
+                let mut rest: Vec<&str> = sub.collect();
+                if rest.is_empty() {
+                    let len = match params.next() {
+                        Some("5") => 1,
+                        Some("2") => 3,
+                        _ => 0,
+                    };
+                    rest.push(if len == 1 { "5" } else { "2" });
+                    rest.extend(params.by_ref().take(len));
+                }
+                let values: Vec<u8> = rest.iter().filter_map(|v| v.parse().ok()).collect();
+                let color = match values[..] {
+                    [5, index] => Color::Ansi256(Ansi256Color(index)),
+                    [2, .., r, g, b] => Color::Rgb(RgbColor(r, g, b)),
+                    _ => continue,
+                };
+                match code {
+                    38 => style.fg_color(Some(color)),
+                    48 => style.bg_color(Some(color)),
+                    _ => style.underline_color(Some(color)),
+                }
+            }
+            _ => style,
+        };
+    }
+    style
+}
+
+fn ansi_color(index: u8, bright: bool) -> Option<Color> {
+    const COLORS: [AnsiColor; 8] = [
+        AnsiColor::Black,
+        AnsiColor::Red,
+        AnsiColor::Green,
+        AnsiColor::Yellow,
+        AnsiColor::Blue,
+        AnsiColor::Magenta,
+        AnsiColor::Cyan,
+        AnsiColor::White,
+    ];
+    Some(Color::Ansi(COLORS[usize::from(index)].bright(bright)))
+}
+
+fn write_tspan(out: &mut String, class: Option<&str>, text: &str) {
+    match class {
+        Some(class) => write!(out, r#"<tspan class="{class}">{text}</tspan>"#).unwrap(),
+        None => write!(out, "<tspan>{text}</tspan>").unwrap(),
+    }
+}
+
+const ANSI_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright-black",
+    "bright-red",
+    "bright-green",
+    "bright-yellow",
+    "bright-blue",
+    "bright-magenta",
+    "bright-cyan",
+    "bright-white",
+];
+
+fn color_class(prefix: &str, color: Color) -> String {
+    match color {
+        Color::Ansi(color) => format!("{prefix}-{}", ANSI_NAMES[color as usize]),
+        Color::Ansi256(Ansi256Color(index)) => format!("{prefix}-ansi256-{index:03}"),
+        Color::Rgb(RgbColor(r, g, b)) => format!("{prefix}-rgb-{r:02X}{g:02X}{b:02X}"),
+    }
+}
+
+fn hex(RgbColor(r, g, b): RgbColor) -> String {
+    format!("#{r:02X}{g:02X}{b:02X}")
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
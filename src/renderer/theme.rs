@@ -0,0 +1,251 @@
+//! Preset styles for [`Renderer::theme`] and downgrading them per [`Renderer::color_support`]
+
+use anstyle::{Ansi256Color, AnsiColor, Color, Effects, RgbColor, Style};
+
+use super::stylesheet::Stylesheet;
+use super::svg::Palette;
+use super::{
+    DEFAULT_ADDITION_STYLE, DEFAULT_CONTEXT_STYLE, DEFAULT_EMPHASIS_STYLE, DEFAULT_ERROR_STYLE,
+    DEFAULT_HELP_STYLE, DEFAULT_INFO_STYLE, DEFAULT_LINE_NUM_STYLE, DEFAULT_NONE_STYLE,
+    DEFAULT_NOTE_STYLE, DEFAULT_REMOVAL_STYLE, DEFAULT_WARNING_STYLE,
+};
+
+/// Styles for every part of a [`Renderer`]'s output, see [`Renderer::theme`]
+///
+/// Individual styles can still be overridden afterwards, e.g. with [`Renderer::error`].
+///
+/// [`Renderer`]: super::Renderer
+/// [`Renderer::theme`]: super::Renderer::theme
+/// [`Renderer::error`]: super::Renderer::error
+#[derive(Clone, Copy, Debug)]
+pub struct Theme {
+    pub(crate) stylesheet: Stylesheet,
+}
+
+impl Theme {
+    /// The styles of [`Renderer::styled`][super::Renderer::styled]
+    pub const DEFAULT: Self = Self {
+        stylesheet: Stylesheet {
+            error: DEFAULT_ERROR_STYLE,
+            warning: DEFAULT_WARNING_STYLE,
+            info: DEFAULT_INFO_STYLE,
+            note: DEFAULT_NOTE_STYLE,
+            help: DEFAULT_HELP_STYLE,
+            line_num: DEFAULT_LINE_NUM_STYLE,
+            emphasis: DEFAULT_EMPHASIS_STYLE,
+            none: DEFAULT_NONE_STYLE,
+            context: DEFAULT_CONTEXT_STYLE,
+            addition: DEFAULT_ADDITION_STYLE,
+            removal: DEFAULT_REMOVAL_STYLE,
+        },
+    };
+
+    /// Bright colors that stand out on a dark background
+    pub const DARK: Self = Self {
+        stylesheet: Stylesheet {
+            error: bold(AnsiColor::BrightRed),
+            warning: bold(AnsiColor::BrightYellow),
+            info: bold(AnsiColor::BrightBlue),
+            note: bold(AnsiColor::BrightGreen),
+            help: bold(AnsiColor::BrightCyan),
+            line_num: bold(AnsiColor::BrightBlue),
+            emphasis: bold(AnsiColor::BrightWhite),
+            none: Style::new(),
+            context: bold(AnsiColor::BrightBlue),
+            addition: AnsiColor::BrightGreen.on_default(),
+            removal: AnsiColor::BrightRed.on_default(),
+        },
+    };
+
+    /// Dark colors that stay readable on a light background, without yellow
+    pub const LIGHT: Self = Self {
+        stylesheet: Stylesheet {
+            error: bold(AnsiColor::Red),
+            warning: bold(AnsiColor::Magenta),
+            info: bold(AnsiColor::Blue),
+            note: bold(AnsiColor::Green),
+            help: bold(AnsiColor::Cyan),
+            line_num: bold(AnsiColor::Blue),
+            emphasis: Style::new().effects(Effects::BOLD),
+            none: Style::new(),
+            context: bold(AnsiColor::Blue),
+            addition: AnsiColor::Green.on_default(),
+            removal: AnsiColor::Red.on_default(),
+        },
+    };
+
+    /// Bold, bright colors for everything, with titles also underlined
+    ///
+    /// Notes and line numbers are bold in the terminal's own foreground color, so they stay
+    /// visible on both dark and light backgrounds.
+    pub const HIGH_CONTRAST: Self = Self {
+        stylesheet: Stylesheet {
+            error: bold(AnsiColor::BrightRed),
+            warning: bold(AnsiColor::BrightYellow),
+            info: bold(AnsiColor::BrightCyan),
+            note: Style::new().effects(Effects::BOLD),
+            help: bold(AnsiColor::BrightGreen),
+            line_num: Style::new().effects(Effects::BOLD),
+            emphasis: Style::new().effects(Effects::BOLD.insert(Effects::UNDERLINE)),
+            none: Style::new(),
+            context: bold(AnsiColor::BrightCyan),
+            addition: bold(AnsiColor::BrightGreen),
+            removal: bold(AnsiColor::BrightRed),
+        },
+    };
+
+    /// Blue and yellow instead of green and red, which are hard to tell apart with the most
+    /// common color vision deficiencies
+    pub const COLOR_BLIND: Self = Self {
+        stylesheet: Stylesheet {
+            error: bold(AnsiColor::BrightYellow),
+            warning: bold(AnsiColor::BrightMagenta),
+            info: bold(AnsiColor::BrightBlue),
+            note: Style::new().effects(Effects::BOLD),
+            help: bold(AnsiColor::BrightCyan),
+            line_num: bold(AnsiColor::BrightBlue),
+            emphasis: Style::new().effects(Effects::BOLD),
+            none: Style::new(),
+            context: bold(AnsiColor::BrightBlue),
+            addition: AnsiColor::BrightBlue.on_default(),
+            removal: AnsiColor::BrightYellow
+                .on_default()
+                .effects(Effects::STRIKETHROUGH),
+        },
+    };
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+const fn bold(color: AnsiColor) -> Style {
+    color.on_default().effects(Effects::BOLD)
+}
+
+/// The colors a terminal can show, see [`Renderer::color_support`][super::Renderer::color_support]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorSupport {
+    /// No colors, only [bold][Effects::BOLD] and [underlined][Effects::UNDERLINE] text
+    Monochrome,
+    /// The 16 [`AnsiColor`]s
+    Ansi16,
+    /// The 256 [`Ansi256Color`]s
+    Ansi256,
+    /// Any [`RgbColor`]
+    TrueColor,
+}
+
+impl Stylesheet {
+    /// Replace the colors that `support` lacks with the closest ones it has
+    pub(crate) fn downgrade(self, support: ColorSupport) -> Self {
+        if support == ColorSupport::TrueColor {
+            return self;
+        }
+        let downgrade = |style: Style| downgrade_style(style, support);
+        Self {
+            error: downgrade(self.error),
+            warning: downgrade(self.warning),
+            info: downgrade(self.info),
+            note: downgrade(self.note),
+            help: downgrade(self.help),
+            line_num: downgrade(self.line_num),
+            emphasis: downgrade(self.emphasis),
+            none: downgrade(self.none),
+            context: downgrade(self.context),
+            addition: downgrade(self.addition),
+            removal: downgrade(self.removal),
+        }
+    }
+}
+
+fn downgrade_style(style: Style, support: ColorSupport) -> Style {
+    if support == ColorSupport::Monochrome {
+        let effects = style.get_effects();
+        let underlined = UNDERLINES
+            .iter()
+            .any(|underline| effects.contains(*underline));
+        return Style::new().effects(
+            Effects::new()
+                .set(Effects::BOLD, effects.contains(Effects::BOLD))
+                .set(Effects::UNDERLINE, underlined),
+        );
+    }
+    let downgrade = |color: Option<Color>| color.map(|color| downgrade_color(color, support));
+    style
+        .fg_color(downgrade(style.get_fg_color()))
+        .bg_color(downgrade(style.get_bg_color()))
+        .underline_color(downgrade(style.get_underline_color()))
+}
+
+/// Shown as a plain underline by [`ColorSupport::Monochrome`]
+const UNDERLINES: [Effects; 5] = [
+    Effects::UNDERLINE,
+    Effects::DOUBLE_UNDERLINE,
+    Effects::CURLY_UNDERLINE,
+    Effects::DOTTED_UNDERLINE,
+    Effects::DASHED_UNDERLINE,
+];
+
+/// The 16 [`AnsiColor`]s, in the order of their [`Ansi256Color`] indexes
+const ANSI_COLORS: [AnsiColor; 16] = [
+    AnsiColor::Black,
+    AnsiColor::Red,
+    AnsiColor::Green,
+    AnsiColor::Yellow,
+    AnsiColor::Blue,
+    AnsiColor::Magenta,
+    AnsiColor::Cyan,
+    AnsiColor::White,
+    AnsiColor::BrightBlack,
+    AnsiColor::BrightRed,
+    AnsiColor::BrightGreen,
+    AnsiColor::BrightYellow,
+    AnsiColor::BrightBlue,
+    AnsiColor::BrightMagenta,
+    AnsiColor::BrightCyan,
+    AnsiColor::BrightWhite,
+];
+
+/// The closest color to `color` that `support` has, by the RGB values of [`Palette::VGA`]
+fn downgrade_color(color: Color, support: ColorSupport) -> Color {
+    let palette = Palette::VGA;
+    match (color, support) {
+        (Color::Ansi(_), _) => color,
+        (Color::Ansi256(Ansi256Color(index)), ColorSupport::Ansi16) => {
+            let rgb = palette.rgb(color);
+            Color::Ansi(
+                ANSI_COLORS
+                    .get(usize::from(index))
+                    .copied()
+                    .unwrap_or_else(|| closest_ansi(&palette, rgb)),
+            )
+        }
+        (Color::Ansi256(_), _) => color,
+        (Color::Rgb(rgb), ColorSupport::Ansi16) => Color::Ansi(closest_ansi(&palette, rgb)),
+        (Color::Rgb(rgb), _) => {
+            // Terminals let users pick the first 16 colors, so they can't be relied on
+            let index = (16..=255)
+                .min_by_key(|index| {
+                    distance(palette.rgb(Color::Ansi256(Ansi256Color(*index))), rgb)
+                })
+                .unwrap();
+            Color::Ansi256(Ansi256Color(index))
+        }
+    }
+}
+
+fn closest_ansi(palette: &Palette, rgb: RgbColor) -> AnsiColor {
+    ANSI_COLORS
+        .into_iter()
+        .min_by_key(|ansi| distance(palette.rgb(Color::Ansi(*ansi)), rgb))
+        .unwrap()
+}
+
+/// The squared distance between two colors
+fn distance(a: RgbColor, b: RgbColor) -> u32 {
+    let channel = |a: u8, b: u8| u32::from(a.abs_diff(b)).pow(2);
+    channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
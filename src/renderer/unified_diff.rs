@@ -0,0 +1,142 @@
+//! Render the [`Patch`]es of a [`Snippet`] as a unified diff, e.g. for `git apply`
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::cmp::min;
+use core::fmt::Write as _;
+
+use super::Renderer;
+use crate::renderer::source_map::SourceMap;
+use crate::{Applicability, Patch, Snippet};
+
+/// Lines replaced at `start`, an index into the source's lines
+struct Change<'a> {
+    start: usize,
+    removed: Vec<&'a str>,
+    added: Vec<&'a str>,
+}
+
+pub(crate) fn render_unified_diff(renderer: &Renderer, snippet: &Snippet<'_, Patch<'_>>) -> String {
+    let context = renderer.diff_context_lines;
+    let source = snippet.source.as_ref();
+    let lines = source.split_inclusive('\n').collect::<Vec<_>>();
+    let sm = SourceMap::new(source, snippet.line_start, renderer.width());
+    let hunks = sm.patched_hunks(&snippet.markers);
+
+    let mut changes = vec![];
+    for hunk in &hunks {
+        let start = hunk.line - snippet.line_start;
+        let original = hunk.original.split_inclusive('\n').collect::<Vec<_>>();
+        let patched = hunk.patched.split_inclusive('\n').collect::<Vec<_>>();
+        let prefix = original
+            .iter()
+            .zip(&patched)
+            .take_while(|(o, p)| o == p)
+            .count();
+        let suffix = original[prefix..]
+            .iter()
+            .rev()
+            .zip(patched[prefix..].iter().rev())
+            .take_while(|(o, p)| o == p)
+            .count();
+        let removed = original[prefix..original.len() - suffix].to_vec();
+        let added = patched[prefix..patched.len() - suffix].to_vec();
+        if removed.is_empty() && added.is_empty() {
+            continue;
+        }
+        changes.push(Change {
+            start: start + prefix,
+            removed,
+            added,
+        });
+    }
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    // `git apply` and `patch` skip text before the file header
+    if snippet.applicability != Applicability::Unspecified {
+        writeln!(out, "Applicability: {}", snippet.applicability.as_str()).unwrap();
+    }
+    // Hunks without a file header are not a valid diff
+    let path = snippet.path.as_deref().unwrap_or("<anonymous>");
+    writeln!(out, "--- a/{path}").unwrap();
+    writeln!(out, "+++ b/{path}").unwrap();
+
+    // Changes close enough for their context to touch share a hunk
+    let mut hunk_changes: Vec<Vec<Change<'_>>> = vec![];
+    for change in changes {
+        match hunk_changes.last_mut().and_then(|hunk| hunk.last()) {
+            Some(last) if change.start <= last.start + last.removed.len() + 2 * context => {
+                hunk_changes.last_mut().unwrap().push(change);
+            }
+            _ => hunk_changes.push(vec![change]),
+        }
+    }
+
+    // Lines added and removed by earlier hunks, which shift the new line numbers
+    let (mut total_added, mut total_removed) = (0, 0);
+    for changes in hunk_changes {
+        let first = changes.first().unwrap();
+        let last = changes.last().unwrap();
+        let start = first.start.saturating_sub(context);
+        let end = min(last.start + last.removed.len() + context, lines.len());
+
+        let mut body = String::new();
+        let mut pos = start;
+        let (mut added, mut removed) = (0, 0);
+        for change in &changes {
+            for line in &lines[pos..change.start] {
+                push_line(&mut body, ' ', line);
+            }
+            for line in &change.removed {
+                push_line(&mut body, '-', line);
+            }
+            for line in &change.added {
+                push_line(&mut body, '+', line);
+            }
+            added += change.added.len();
+            removed += change.removed.len();
+            pos = change.start + change.removed.len();
+        }
+        for line in &lines[pos..end] {
+            push_line(&mut body, ' ', line);
+        }
+
+        let old_len = end - start;
+        let new_len = old_len - removed + added;
+        let old_start = snippet.line_start + start;
+        let new_start = old_start + total_added - total_removed;
+        writeln!(
+            out,
+            "@@ -{} +{} @@",
+            range(old_start, old_len),
+            range(new_start, new_len)
+        )
+        .unwrap();
+        out.push_str(&body);
+        total_added += added;
+        total_removed += removed;
+    }
+    out
+}
+
+/// `start,len`, where an empty range starts at the line before it and a
+/// length of one is implied
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start.saturating_sub(1)),
+        1 => format!("{start}"),
+        _ => format!("{start},{len}"),
+    }
+}
+
+fn push_line(out: &mut String, prefix: char, line: &str) {
+    out.push(prefix);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
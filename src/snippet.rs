@@ -6,7 +6,11 @@ use alloc::{vec, vec::Vec};
 use core::ops::Range;
 
 use crate::Level;
-use crate::renderer::source_map::{TrimmedPatch, as_substr};
+use crate::binary::{BinaryView, ByteMap};
+use crate::gutter::LineLabels;
+use crate::language::{LanguageProfile, Rust};
+use crate::renderer::source_map::{TrimmedPatch, as_substr, splice};
+use crate::source::{Excerpt, SourceChunk};
 
 pub(crate) const ERROR_TXT: &str = "error";
 pub(crate) const HELP_TXT: &str = "help";
@@ -93,6 +97,53 @@ impl<'a> Group<'a> {
     pub fn is_empty(&self) -> bool {
         self.elements.is_empty() && self.title.is_none()
     }
+
+    /// The [`Element::Suggestion`] shown in the [`Title`] and its code, see
+    /// [`SuggestionStyle::Inline`]
+    pub(crate) fn inline_suggestion(&self) -> Option<(usize, &str)> {
+        self.title.as_ref()?;
+        self.elements
+            .iter()
+            .enumerate()
+            .find_map(|(i, element)| match element {
+                Element::Suggestion(suggestion) => Some((i, suggestion.inline_code()?)),
+                _ => None,
+            })
+    }
+
+    /// The [`Applicability`] that all of the group's suggestions share
+    ///
+    /// When it is [`None`], each suggestion is marked on its own, see
+    /// [`Renderer::applicability_marker`][crate::Renderer::applicability_marker].
+    pub(crate) fn applicability(&self) -> Option<Applicability> {
+        let mut files = self.suggestion_files();
+        let applicability = files.next()?.applicability;
+        files
+            .all(|file| file.applicability == applicability)
+            .then_some(applicability)
+    }
+
+    /// The markers of all of the group's suggestions, for showing them on one line
+    pub(crate) fn applicability_markers(&self) -> Option<String> {
+        let mut markers: Vec<&str> = Vec::new();
+        for marker in self
+            .suggestion_files()
+            .filter_map(|file| file.applicability.marker())
+        {
+            if !markers.contains(&marker) {
+                markers.push(marker);
+            }
+        }
+        (!markers.is_empty()).then(|| markers.join(" "))
+    }
+
+    fn suggestion_files(&self) -> impl Iterator<Item = &Snippet<'a, Patch<'a>>> {
+        self.elements.iter().flat_map(|element| match element {
+            Element::Suggestion(suggestion) => core::slice::from_ref(suggestion),
+            Element::MultiFileSuggestion(suggestion) => &suggestion.files[..],
+            _ => &[],
+        })
+    }
 }
 
 /// A section of content within a [`Group`]
@@ -102,6 +153,7 @@ pub enum Element<'a> {
     Message(Message<'a>),
     Cause(Snippet<'a, Annotation<'a>>),
     Suggestion(Snippet<'a, Patch<'a>>),
+    MultiFileSuggestion(MultiFileSuggestion<'a>),
     Origin(Origin<'a>),
     Padding(Padding),
 }
@@ -124,6 +176,12 @@ impl<'a> From<Snippet<'a, Patch<'a>>> for Element<'a> {
     }
 }
 
+impl<'a> From<MultiFileSuggestion<'a>> for Element<'a> {
+    fn from(value: MultiFileSuggestion<'a>) -> Self {
+        Element::MultiFileSuggestion(value)
+    }
+}
+
 impl<'a> From<Origin<'a>> for Element<'a> {
     fn from(value: Origin<'a>) -> Self {
         Element::Origin(value)
@@ -226,9 +284,20 @@ pub struct Snippet<'a, T> {
     pub(crate) path: Option<Cow<'a, str>>,
     pub(crate) line_start: usize,
     pub(crate) source: Cow<'a, str>,
+    pub(crate) chunks: Vec<SourceChunk>,
     pub(crate) markers: Vec<T>,
     pub(crate) line_numbering: bool,
+    pub(crate) line_labels: Option<&'a dyn LineLabels>,
     pub(crate) fold: bool,
+    pub(crate) context_lines: (usize, usize),
+    pub(crate) multiline_lines: usize,
+    pub(crate) max_lines: Option<usize>,
+    pub(crate) language: &'a dyn LanguageProfile,
+    pub(crate) applicability: Applicability,
+    pub(crate) suggestion_style: SuggestionStyle,
+    pub(crate) byte_map: Option<ByteMap>,
+    pub(crate) visible_whitespace: bool,
+    pub(crate) reveal_invisible: bool,
 }
 
 impl<'a, T: Clone> Snippet<'a, T> {
@@ -246,9 +315,20 @@ impl<'a, T: Clone> Snippet<'a, T> {
             path: None,
             line_start: 1,
             source: source.into(),
+            chunks: vec![],
             markers: vec![],
             line_numbering: true,
+            line_labels: None,
             fold: true,
+            context_lines: (0, 0),
+            multiline_lines: 4,
+            max_lines: None,
+            language: &Rust,
+            applicability: Applicability::Unspecified,
+            suggestion_style: SuggestionStyle::Auto,
+            byte_map: None,
+            visible_whitespace: false,
+            reveal_invisible: false,
         }
     }
 
@@ -260,6 +340,24 @@ impl<'a, T: Clone> Snippet<'a, T> {
         self
     }
 
+    /// Show labels from `labels` in the gutter instead of line numbers
+    ///
+    /// Hidden with [`line_numbering(false)`][Self::line_numbering].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use annotate_snippets::*;
+    /// # use annotate_snippets::gutter::Prefixed;
+    /// let snippet = Snippet::source("print(1 +)")
+    ///     .line_labels(&const { Prefixed::new("In[3]:") })
+    ///     .annotation(AnnotationKind::Primary.span(9..10));
+    /// ```
+    pub fn line_labels(mut self, labels: &'a dyn LineLabels) -> Self {
+        self.line_labels = Some(labels);
+        self
+    }
+
     /// When manually [`fold`][Self::fold]ing,
     /// the [`source`][Self::source]s line offset from the original start
     pub fn line_start(mut self, line_start: usize) -> Self {
@@ -290,18 +388,148 @@ impl<'a, T: Clone> Snippet<'a, T> {
         self.fold = fold;
         self
     }
+
+    /// Whether the source is a [`BinaryView::Hexdump`], which has no lines and columns
+    pub(crate) fn is_hexdump(&self) -> bool {
+        matches!(self.byte_map, Some(ByteMap::Hexdump { .. }))
+    }
 }
 
 impl<'a> Snippet<'a, Annotation<'a>> {
+    /// The lines of a source too large to load, see [`SourceProvider::excerpt`]
+    ///
+    /// [`Annotation`] spans are byte offsets into the whole source.
+    /// Annotations outside the lines the [`Excerpt`] has are not shown.
+    /// Line numbers are taken from the [`Excerpt`], rather than [`line_start`][Self::line_start].
+    ///
+    /// [`SourceProvider::excerpt`]: crate::source::SourceProvider::excerpt
+    pub fn excerpt(excerpt: Excerpt) -> Self {
+        let line_start = excerpt.chunks.first().map_or(1, |chunk| chunk.line);
+        let mut snippet = Self::source(excerpt.text).line_start(line_start);
+        snippet.chunks = excerpt.chunks;
+        snippet
+    }
+
+    /// Bytes that are not necessarily UTF-8, e.g. an object file or corrupted text
+    ///
+    /// [`Annotation`] spans are byte offsets into `bytes`.
+    /// A span into an invalid UTF-8 sequence shown as `�` covers the whole `�`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use annotate_snippets::*;
+    /// # use annotate_snippets::binary::BinaryView;
+    /// let bytes = b"\x7fELF\x02\x01\x01\x00";
+    /// let report = &[Level::ERROR.primary_title("unsupported ELF version").element(
+    ///     Snippet::binary(bytes, BinaryView::Hexdump)
+    ///         .path("a.out")
+    ///         .annotation(AnnotationKind::Primary.span(6..7).label("expected 1")),
+    /// )];
+    /// ```
+    pub fn binary(bytes: &[u8], view: BinaryView) -> Self {
+        let (text, byte_map) = ByteMap::new(bytes, view);
+        let mut snippet = Self::source(text);
+        // Each row starts with its offset
+        snippet.line_numbering = view != BinaryView::Hexdump;
+        snippet.byte_map = Some(byte_map);
+        snippet
+    }
+
     /// Highlight and describe a span of text within the [`source`][Self::source]
     pub fn annotation(mut self, annotation: Annotation<'a>) -> Self {
-        self.markers.push(annotation);
+        match &self.byte_map {
+            Some(byte_map) => self.markers.extend(byte_map.annotations(annotation)),
+            None => self.markers.push(annotation),
+        }
         self
     }
 
     /// Highlight and describe spans of text within the [`source`][Self::source]
     pub fn annotations(mut self, annotation: impl IntoIterator<Item = Annotation<'a>>) -> Self {
-        self.markers.extend(annotation);
+        for annotation in annotation {
+            self = self.annotation(annotation);
+        }
+        self
+    }
+
+    /// When [folding][Self::fold], also show `before` lines above and `after` lines below each
+    /// shown line
+    ///
+    /// Overlapping context is merged and the remaining gaps are still elided with `...`, like
+    /// `grep --before-context` and `--after-context`.
+    ///
+    /// The default is `context_lines(0, 0)`.
+    pub fn context_lines(mut self, before: usize, after: usize) -> Self {
+        self.context_lines = (before, after);
+        self
+    }
+
+    /// How many lines of a multiline [`Annotation`] to show from its start before skipping to
+    /// its end
+    ///
+    /// Trailing [insignificant][LanguageProfile::is_insignificant] lines are not shown.
+    ///
+    /// The default is `multiline_lines(4)`.
+    pub fn multiline_lines(mut self, lines: usize) -> Self {
+        self.multiline_lines = lines;
+        self
+    }
+
+    /// Show at most `lines` lines of the [`source`][Self::source], summarizing how many
+    /// annotated lines are left out after them
+    ///
+    /// With `max_lines(0)`, only the summary is shown.
+    /// The default is to show all annotated lines.
+    pub fn max_lines(mut self, lines: usize) -> Self {
+        self.max_lines = Some(lines);
+        self
+    }
+
+    /// The language of the [`source`][Self::source], for which lines to skip in multiline
+    /// [`Annotation`]s
+    ///
+    /// The default is [`Rust`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use annotate_snippets::*;
+    /// # use annotate_snippets::language::LineComments;
+    /// let source = "SELECT name\n-- all users\nFROM users\n";
+    /// let snippet = Snippet::source(source)
+    ///     .language(&const { LineComments::new(&["--"]) })
+    ///     .annotation(AnnotationKind::Primary.span(0..source.len() - 1));
+    /// ```
+    pub fn language(mut self, language: &'a dyn LanguageProfile) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Show whitespace in the source lines as visible glyphs
+    ///
+    /// Spaces are shown as `·`, tabs as `→` and a `\r\n` line ending as `␍`, so trailing
+    /// whitespace, mixed indentation and line endings can be annotated. Each glyph takes the
+    /// columns of the whitespace it replaces, so [`Annotation`]s still line up. With
+    /// [`AmbiguousWidth::Wide`][crate::renderer::AmbiguousWidth::Wide], where `·` and `→` take two
+    /// columns, spaces are shown as `.` and tabs as `>` instead.
+    ///
+    /// The default is `visible_whitespace(false)`.
+    pub fn visible_whitespace(mut self, yes: bool) -> Self {
+        self.visible_whitespace = yes;
+        self
+    }
+
+    /// Point out bidirectional control characters and other invisible characters in the
+    /// source, which can make it look different from what it is (see "Trojan Source")
+    ///
+    /// Each one gets an [`AnnotationKind::Context`] [`Annotation`] naming it, e.g.
+    /// `U+202E RIGHT-TO-LEFT OVERRIDE`. Their lines are shown even when
+    /// [folding][Self::fold].
+    ///
+    /// The default is `reveal_invisible(false)`.
+    pub fn reveal_invisible(mut self, yes: bool) -> Self {
+        self.reveal_invisible = yes;
         self
     }
 }
@@ -318,6 +546,74 @@ impl<'a> Snippet<'a, Patch<'a>> {
         self.markers.extend(patches);
         self
     }
+
+    /// How confident the [`Patch`]es are to be correct
+    ///
+    /// The default is [`Applicability::Unspecified`].
+    ///
+    /// See [`Renderer::applicability_marker`][crate::Renderer::applicability_marker] for showing
+    /// it. [`Renderer::render_unified_diff`][crate::Renderer::render_unified_diff] writes it
+    /// for tools that apply the diff.
+    pub fn applicability(mut self, applicability: Applicability) -> Self {
+        self.applicability = applicability;
+        self
+    }
+
+    /// How to show the [`Patch`]es
+    ///
+    /// The default is [`SuggestionStyle::Auto`].
+    pub fn suggestion_style(mut self, style: SuggestionStyle) -> Self {
+        self.suggestion_style = style;
+        self
+    }
+
+    /// The replacement to show in the [`Title`], see [`SuggestionStyle::Inline`]
+    pub(crate) fn inline_code(&self) -> Option<&str> {
+        match &self.markers[..] {
+            [patch]
+                if self.suggestion_style == SuggestionStyle::Inline
+                    && !patch.replacement.is_empty()
+                    && !patch.replacement.contains('\n') =>
+            {
+                Some(&patch.replacement)
+            }
+            _ => None,
+        }
+    }
+
+    /// The [`source`][Self::source] with all [`Patch`]es applied
+    pub(crate) fn apply(&self) -> Result<String, PatchError> {
+        let mut patches = self.markers.iter().collect::<Vec<_>>();
+        patches.sort_by_key(|p| (p.span.start, p.span.end));
+
+        let error = |span: &Range<usize>, reason| PatchError {
+            path: self.path.as_deref().map(ToOwned::to_owned),
+            span: span.clone(),
+            reason,
+        };
+        let mut pos = 0;
+        for patch in &patches {
+            let span = &patch.span;
+            if span.start > span.end || span.end > self.source.len() {
+                return Err(error(span, PatchErrorReason::OutOfBounds));
+            }
+            if !self.source.is_char_boundary(span.start) || !self.source.is_char_boundary(span.end)
+            {
+                return Err(error(span, PatchErrorReason::NotCharBoundary));
+            }
+            if span.start < pos {
+                return Err(error(span, PatchErrorReason::Overlapping));
+            }
+            pos = span.end;
+        }
+        Ok(splice(
+            &self.source,
+            0..self.source.len(),
+            patches
+                .into_iter()
+                .map(|patch| (patch.span.clone(), &*patch.replacement)),
+        ))
+    }
 }
 
 /// Highlight and describe a span of text within a [`Snippet`]
@@ -338,6 +634,7 @@ pub struct Annotation<'a> {
     pub(crate) label: Option<Cow<'a, str>>,
     pub(crate) kind: AnnotationKind,
     pub(crate) highlight_source: bool,
+    pub(crate) insertion: bool,
 }
 
 impl<'a> Annotation<'a> {
@@ -409,6 +706,28 @@ impl AnnotationKind {
             label: None,
             kind: self,
             highlight_source: false,
+            insertion: false,
+        }
+    }
+
+    /// Annotate the point between two characters at byte `offset` within [`Snippet`], e.g. where
+    /// something is missing
+    ///
+    /// The point is marked between the columns around it, like `/\`. At the end of the
+    /// [`source`][Snippet::source], it is marked as `<EOF>` instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use annotate_snippets::*;
+    /// let source = "let x = 1\nlet y = 2;";
+    /// let snippet = Snippet::source(source)
+    ///     .annotation(AnnotationKind::Primary.insertion(9).label("expected `;`"));
+    /// ```
+    pub fn insertion<'a>(self, offset: usize) -> Annotation<'a> {
+        Annotation {
+            insertion: true,
+            ..self.span(offset..offset)
         }
     }
 
@@ -477,6 +796,191 @@ impl<'a> Patch<'a> {
     }
 }
 
+/// How confident a suggestion is that its [`Patch`]es are correct, see
+/// [`Snippet::applicability`]
+///
+/// Tools like `cargo fix` only apply [`Applicability::MachineApplicable`] suggestions without
+/// asking.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Applicability {
+    /// The suggestion is definitely what the user intended, or maintains the exact meaning of
+    /// the code
+    MachineApplicable,
+    /// The suggestion may be what the user intended, but it is uncertain
+    MaybeIncorrect,
+    /// The suggestion contains placeholders like `(...)` or `{ /* fields */ }`, which need to be
+    /// filled in
+    HasPlaceholders,
+    /// The applicability of the suggestion is unknown
+    #[default]
+    Unspecified,
+}
+
+impl Applicability {
+    /// The name rustc uses in its JSON output, e.g. `"MachineApplicable"`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MachineApplicable => "MachineApplicable",
+            Self::MaybeIncorrect => "MaybeIncorrect",
+            Self::HasPlaceholders => "HasPlaceholders",
+            Self::Unspecified => "Unspecified",
+        }
+    }
+
+    /// What [`Renderer::applicability_marker`][crate::Renderer::applicability_marker] shows
+    pub(crate) fn marker(&self) -> Option<&'static str> {
+        match self {
+            Self::MaybeIncorrect => Some("(maybe incorrect)"),
+            Self::HasPlaceholders => Some("(has placeholders)"),
+            Self::MachineApplicable | Self::Unspecified => None,
+        }
+    }
+}
+
+/// How a [`Snippet`] with [`Patch`]es is shown, see [`Snippet::suggestion_style`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SuggestionStyle {
+    /// Pick a style based on the [`Patch`]es
+    ///
+    /// Deletions are shown as a diff, single-line changes are underlined, and added lines are
+    /// shown with `+`.
+    #[default]
+    Auto,
+    /// Show the replacement in the [`Group`]'s [`Title`], e.g. ``help: use: `foo` ``
+    ///
+    /// This only applies to a single, single-line [`Patch`] in a [`Group`] with a [`Title`],
+    /// falling back to [`SuggestionStyle::Auto`] otherwise.
+    Inline,
+    /// Show the removed and added line
+    ///
+    /// Suggestions spanning several lines are shown as with [`SuggestionStyle::Auto`].
+    Diff,
+    /// Show the patched line with the replacements underlined
+    ///
+    /// Suggestions spanning several lines are shown as with [`SuggestionStyle::Auto`].
+    Underline,
+    /// Only show the [`Group`]'s [`Title`], not the code
+    Hidden,
+}
+
+/// A suggested edit [`Element`] that spans several files
+///
+/// Each file is a [`Snippet`] with [`Patch`]es, which are shown as one suggestion, with a header
+/// per file.
+/// Unlike separate [`Snippet`]s, the edits are meant to be applied together, see
+/// [`MultiFileSuggestion::apply`].
+///
+/// # Example
+///
+/// ```rust
+/// # use annotate_snippets::*;
+/// let lib = "pub mod util;\n";
+/// let main = "fn main() {\n    helper();\n}\n";
+/// let report = &[Level::HELP
+///     .secondary_title("consider importing this function")
+///     .element(
+///         MultiFileSuggestion::new()
+///             .file(
+///                 Snippet::source(lib)
+///                     .path("src/lib.rs")
+///                     .patch(Patch::new(0..0, "pub use util::helper;\n")),
+///             )
+///             .file(
+///                 Snippet::source(main)
+///                     .path("src/main.rs")
+///                     .patch(Patch::new(0..0, "use my_crate::helper;\n\n")),
+///             ),
+///     )];
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MultiFileSuggestion<'a> {
+    pub(crate) files: Vec<Snippet<'a, Patch<'a>>>,
+}
+
+impl<'a> MultiFileSuggestion<'a> {
+    /// Create a suggestion without any files, see [`MultiFileSuggestion::file`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Suggest edits to another file
+    pub fn file(mut self, file: Snippet<'a, Patch<'a>>) -> Self {
+        self.files.push(file);
+        self
+    }
+
+    /// Suggest edits to other files
+    pub fn files(mut self, files: impl IntoIterator<Item = Snippet<'a, Patch<'a>>>) -> Self {
+        self.files.extend(files);
+        self
+    }
+
+    /// Apply all [`Patch`]es, returning each file's patched [`source`][Snippet::source] in the
+    /// order the files were added
+    ///
+    /// Either every file is patched or, if any [`Patch`] is invalid, none are.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use annotate_snippets::*;
+    /// let suggestion = MultiFileSuggestion::new()
+    ///     .file(Snippet::source("a = 1\n").path("a.toml").patch(Patch::new(4..5, "2")))
+    ///     .file(Snippet::source("b = 1\n").path("b.toml").patch(Patch::new(4..9, "2")));
+    ///
+    /// let error = suggestion.apply().unwrap_err();
+    /// assert_eq!(error.path(), Some("b.toml"));
+    /// ```
+    pub fn apply(&self) -> Result<Vec<String>, PatchError> {
+        self.files.iter().map(Snippet::apply).collect()
+    }
+}
+
+/// A [`Patch`] that cannot be applied to its [`Snippet::source`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchError {
+    path: Option<String>,
+    span: Range<usize>,
+    reason: PatchErrorReason,
+}
+
+impl PatchError {
+    /// The [`Snippet::path`] of the file the [`Patch`] is for
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// The byte span of the [`Patch`]
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+}
+
+impl core::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let Range { start, end } = self.span;
+        write!(f, "cannot apply patch at {start}..{end}")?;
+        if let Some(path) = &self.path {
+            write!(f, " in `{path}`")?;
+        }
+        let reason = match self.reason {
+            PatchErrorReason::OutOfBounds => "span is out of bounds",
+            PatchErrorReason::NotCharBoundary => "span is not on a `char` boundary",
+            PatchErrorReason::Overlapping => "span overlaps another patch",
+        };
+        write!(f, ": {reason}")
+    }
+}
+
+impl core::error::Error for PatchError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PatchErrorReason {
+    OutOfBounds,
+    NotCharBoundary,
+    Overlapping,
+}
+
 /// A source location [`Element`] in a [`Group`]
 ///
 /// If you have source available, see instead [`Snippet`]
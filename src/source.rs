@@ -0,0 +1,249 @@
+//! Sources too large to load into memory, see [`Snippet::excerpt`]
+//!
+//! [`Snippet::excerpt`]: crate::Snippet::excerpt
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Random access to the lines of a source, e.g. a multi-gigabyte log file
+///
+/// See [`SourceProvider::excerpt`] for reading only the lines that are annotated.
+pub trait SourceProvider {
+    /// The error when the source cannot be read
+    type Error;
+
+    /// Read the whole lines covering `span`, a byte range of the source
+    ///
+    /// This is the line containing `span.start` through the line containing `span.end`,
+    /// including their line endings.
+    fn read_lines(&mut self, span: Range<usize>) -> Result<Lines, Self::Error>;
+
+    /// The number of lines in the source, like [`str::lines`]
+    fn line_count(&mut self) -> Result<usize, Self::Error>;
+
+    /// Read the lines covering `spans` for [`Snippet::excerpt`]
+    ///
+    /// Overlapping and nearby lines are read as one chunk, the rest of the source is skipped.
+    ///
+    /// [`Snippet::excerpt`]: crate::Snippet::excerpt
+    fn excerpt(
+        &mut self,
+        spans: impl IntoIterator<Item = Range<usize>>,
+    ) -> Result<Excerpt, Self::Error>
+    where
+        Self: Sized,
+    {
+        let mut spans = spans.into_iter().collect::<Vec<_>>();
+        spans.sort_by_key(|span| span.start);
+
+        let mut chunks: Vec<Lines> = Vec::new();
+        for span in spans {
+            let lines = self.read_lines(span)?;
+            if let Some(last) = chunks.last_mut() {
+                let last_end = last.offset + last.text.len();
+                // A lone line between chunks would be shown rather than elided, so read it too
+                if lines.line <= last.next_line() + 1 {
+                    let end = lines.offset + lines.text.len();
+                    if last_end < end {
+                        let tail = self.read_lines(last_end..end - 1)?;
+                        last.text.push_str(&tail.text);
+                    }
+                    continue;
+                }
+            }
+            chunks.push(lines);
+        }
+
+        let mut excerpt = Excerpt::default();
+        for lines in chunks {
+            excerpt.chunks.push(SourceChunk {
+                line: lines.line,
+                offset: lines.offset,
+                len: lines.text.len(),
+            });
+            excerpt.text.push_str(&lines.text);
+        }
+        Ok(excerpt)
+    }
+}
+
+/// Whole lines read by a [`SourceProvider`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Lines {
+    /// The (1-based) line number of the first line
+    pub line: usize,
+    /// The byte offset of the first line in the source
+    pub offset: usize,
+    /// The lines, including their line endings
+    pub text: String,
+}
+
+impl Lines {
+    /// The line number after the last line
+    fn next_line(&self) -> usize {
+        self.line + self.text.matches('\n').count()
+    }
+}
+
+/// The lines of a source covering some spans, see [`SourceProvider::excerpt`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Excerpt {
+    pub(crate) text: String,
+    pub(crate) chunks: Vec<SourceChunk>,
+}
+
+/// Consecutive lines of an [`Excerpt`], stored in order in its text
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SourceChunk {
+    /// The (1-based) line number of the first line
+    pub(crate) line: usize,
+    /// The byte offset of the first line in the source
+    pub(crate) offset: usize,
+    /// The length of the lines in bytes
+    pub(crate) len: usize,
+}
+
+#[cfg(feature = "std")]
+pub use file::FileSource;
+
+#[cfg(feature = "std")]
+mod file {
+    use alloc::string::String;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::cmp::min;
+    use core::ops::Range;
+    use std::fs::File;
+    use std::io::{self, Read, Seek, SeekFrom};
+    use std::path::Path;
+
+    use super::{Lines, SourceProvider};
+
+    /// How much is read at a time
+    const BUFFER_SIZE: usize = 64 * 1024;
+    /// How far apart the line starts to resume counting lines from are, at least
+    const CHECKPOINT_INTERVAL: usize = 1024 * 1024;
+
+    /// A [`SourceProvider`] that seeks to and reads only the needed parts of a file
+    ///
+    /// Line numbers are found by counting line endings from the closest known line start, which
+    /// are remembered along the way, so later reads do not rescan the file.
+    ///
+    /// The lines read must be valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use annotate_snippets::*;
+    /// # use annotate_snippets::source::{FileSource, SourceProvider};
+    /// # fn main() -> std::io::Result<()> {
+    /// let span = 3_000_000_000..3_000_000_010;
+    /// let excerpt = FileSource::open("huge.log")?.excerpt([span.clone()])?;
+    /// let report = &[Level::ERROR.primary_title("unexpected entry").element(
+    ///     Snippet::excerpt(excerpt)
+    ///         .path("huge.log")
+    ///         .annotation(AnnotationKind::Primary.span(span)),
+    /// )];
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Debug)]
+    pub struct FileSource<R = File> {
+        reader: R,
+        /// Line starts and their line numbers, sorted and beginning with the first line
+        checkpoints: Vec<(usize, usize)>,
+    }
+
+    impl FileSource<File> {
+        /// Open the file at `path`
+        pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+            File::open(path).map(Self::new)
+        }
+    }
+
+    impl<R: Read + Seek> FileSource<R> {
+        /// Read from `reader`, e.g. a [`File`] or an in-memory [`io::Cursor`]
+        pub fn new(reader: R) -> Self {
+            Self {
+                reader,
+                checkpoints: vec![(0, 1)],
+            }
+        }
+
+        /// The start and line number of the line containing `offset`
+        fn line_start(&mut self, offset: usize) -> io::Result<(usize, usize)> {
+            let closest = self
+                .checkpoints
+                .partition_point(|(start, _)| *start <= offset)
+                - 1;
+            let (mut start, mut line) = self.checkpoints[closest];
+            self.reader.seek(SeekFrom::Start(start as u64))?;
+
+            let mut buffer = vec![0; BUFFER_SIZE];
+            let mut pos = start;
+            while pos < offset {
+                let len = self
+                    .reader
+                    .read(&mut buffer[..min(BUFFER_SIZE, offset - pos)])?;
+                if len == 0 {
+                    break;
+                }
+                for (i, _) in buffer[..len]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, b)| **b == b'\n')
+                {
+                    line += 1;
+                    start = pos + i + 1;
+                    let (last, _) = *self.checkpoints.last().unwrap();
+                    if last + CHECKPOINT_INTERVAL <= start {
+                        self.checkpoints.push((start, line));
+                    }
+                }
+                pos += len;
+            }
+            Ok((start, line))
+        }
+    }
+
+    impl<R: Read + Seek> SourceProvider for FileSource<R> {
+        type Error = io::Error;
+
+        fn read_lines(&mut self, span: Range<usize>) -> io::Result<Lines> {
+            let (offset, line) = self.line_start(span.start)?;
+            self.reader.seek(SeekFrom::Start(offset as u64))?;
+
+            // Read through the line ending after `span.end`
+            let end = span.end.max(span.start) - offset;
+            let mut bytes = Vec::new();
+            let mut buffer = vec![0; BUFFER_SIZE];
+            loop {
+                let len = self.reader.read(&mut buffer)?;
+                if len == 0 {
+                    break;
+                }
+                let scanned = bytes.len().max(end);
+                bytes.extend_from_slice(&buffer[..len]);
+                if let Some(i) = bytes
+                    .get(scanned..)
+                    .and_then(|rest| rest.iter().position(|b| *b == b'\n'))
+                {
+                    bytes.truncate(scanned + i + 1);
+                    break;
+                }
+            }
+
+            let text = String::from_utf8(bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            Ok(Lines { line, offset, text })
+        }
+
+        fn line_count(&mut self) -> io::Result<usize> {
+            let (start, line) = self.line_start(usize::MAX)?;
+            let len = self.reader.seek(SeekFrom::End(0))?;
+            // A trailing line ending does not start another line
+            Ok(if start as u64 == len { line - 1 } else { line })
+        }
+    }
+}
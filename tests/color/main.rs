@@ -4,6 +4,7 @@ mod ann_multiline;
 mod ann_multiline2;
 mod ann_removed_nl;
 mod ensure_emoji_highlight_width;
+mod ensure_emoji_highlight_width_svg;
 mod first_snippet_is_primary;
 mod fold_ann_multiline;
 mod fold_bad_origin_line;
@@ -28,3 +29,5 @@ mod strip_line;
 mod strip_line_char;
 mod strip_line_non_ws;
 mod styled_title;
+mod styled_title_svg;
+mod theme_high_contrast;
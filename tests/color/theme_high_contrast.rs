@@ -0,0 +1,30 @@
+use annotate_snippets::renderer::{DecorStyle, Theme};
+use annotate_snippets::{AnnotationKind, Group, Level, Patch, Renderer, Snippet};
+
+use snapbox::{assert_data_eq, file};
+
+#[test]
+fn case() {
+    let source = "let x: u8 = 256;";
+    let input = &[
+        Level::ERROR.primary_title("literal out of range").element(
+            Snippet::source(source)
+                .path("src/lib.rs")
+                .annotation(AnnotationKind::Primary.span(12..15).label("out of range"))
+                .annotation(AnnotationKind::Context.span(7..9).label("for this type")),
+        ),
+        Group::with_title(Level::HELP.secondary_title("use a wider type")).element(
+            Snippet::source(source)
+                .path("src/lib.rs")
+                .patch(Patch::new(7..9, "u16")),
+        ),
+    ];
+
+    let expected_ascii = file!["theme_high_contrast.ascii.term.svg": TermSvg];
+    let renderer = Renderer::styled().theme(Theme::HIGH_CONTRAST);
+    assert_data_eq!(renderer.render(input), expected_ascii);
+
+    let expected_unicode = file!["theme_high_contrast.unicode.term.svg": TermSvg];
+    let renderer = renderer.decor_style(DecorStyle::Unicode);
+    assert_data_eq!(renderer.render(input), expected_unicode);
+}
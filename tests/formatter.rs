@@ -1,9 +1,19 @@
 use annotate_snippets::{
-    Annotation, AnnotationKind, Group, Level, Padding, Patch, Renderer, Snippet,
+    Annotation, AnnotationKind, Applicability, Group, Level, MultiFileSuggestion, Origin, Padding,
+    Patch, Renderer, Snippet, SuggestionStyle,
 };
 
-use annotate_snippets::renderer::DecorStyle;
-use snapbox::{assert_data_eq, str};
+use annotate_snippets::binary::BinaryView;
+use annotate_snippets::gutter::Prefixed;
+use annotate_snippets::language::LineComments;
+use annotate_snippets::renderer::{
+    AmbiguousWidth, Ansi256Color, AnsiColor, ColorSupport, DecorStyle, Effects, MarkdownSuggestion,
+    RgbColor, SemanticStyle, Severity, ShortMessageFormat, Theme, Trim, TrimMode,
+};
+use annotate_snippets::source::{FileSource, SourceProvider};
+use snapbox::{IntoData as _, assert_data_eq, str};
+use std::io::Cursor;
+use std::panic::{AssertUnwindSafe, catch_unwind};
 
 #[test]
 fn test_i_29() {
@@ -5499,3 +5509,2199 @@ fn wide_chars_in_level_name() {
     let renderer = renderer.decor_style(DecorStyle::Unicode);
     assert_data_eq!(renderer.render(report), expected_unicode);
 }
+
+#[test]
+fn short_message_gcc_format() {
+    let source = r#"fn foo(_: u32) {}
+
+fn main() {
+    foo("Bonjour".to_owned());
+}
+"#;
+    let report = &[
+        Level::ERROR
+            .primary_title("mismatched types")
+            .id("E0308")
+            .element(
+                Snippet::source(source)
+                    .path("src/main.rs")
+                    .annotation(
+                        AnnotationKind::Primary
+                            .span(39..59)
+                            .label("expected `u32`, found `String`"),
+                    )
+                    .annotation(
+                        AnnotationKind::Context
+                            .span(35..38)
+                            .label("arguments to this function are incorrect"),
+                    ),
+            )
+            .element(Level::NOTE.message("expected type `u32`\n   found struct `String`")),
+        Level::NOTE
+            .secondary_title("function defined here")
+            .element(
+                Snippet::source(source)
+                    .path("src/main.rs")
+                    .annotation(AnnotationKind::Primary.span(3..6)),
+            ),
+        Group::with_title(Level::HELP.secondary_title("consider removing the method call")),
+    ];
+
+    let expected = str![[r#"
+src/main.rs:4:9: error: mismatched types: expected `u32`, found `String` [E0308]
+src/main.rs:4:9: note: expected type `u32`    found struct `String`
+src/main.rs:1:4: note: function defined here
+src/main.rs:4:9: help: consider removing the method call
+"#]];
+    let renderer = Renderer::plain()
+        .short_message(true)
+        .short_message_format(ShortMessageFormat::Gcc);
+    assert_data_eq!(renderer.render(report), expected);
+
+    let expected = str![[r#"
+src/main.rs(4,9): error E0308: mismatched types: expected `u32`, found `String`
+src/main.rs(4,9): note: expected type `u32`    found struct `String`
+src/main.rs(1,4): note: function defined here
+src/main.rs(4,9): help: consider removing the method call
+"#]];
+    let renderer = renderer.short_message_format(ShortMessageFormat::Msvc);
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn short_message_without_title() {
+    let report = &[Group::with_level(Level::ERROR).element(Level::NOTE.message("no title"))];
+
+    for format in [
+        ShortMessageFormat::Rustc,
+        ShortMessageFormat::Gcc,
+        ShortMessageFormat::Msvc,
+    ] {
+        let renderer = Renderer::plain()
+            .short_message(true)
+            .short_message_format(format);
+        let result = catch_unwind(AssertUnwindSafe(|| renderer.render(report)));
+        let message = *result.unwrap_err().downcast::<&str>().unwrap();
+        assert_eq!(message, "Expected a Title", "{format:?}");
+    }
+}
+
+#[test]
+fn short_message_gcc_format_origin() {
+    let report = &[Level::WARNING
+        .primary_title("unused manifest key")
+        .element(Origin::path("Cargo.toml").line(3))
+        .element(Level::NOTE.no_name().message("remove it"))];
+
+    let expected = str![[r#"
+Cargo.toml:3: warning: unused manifest key
+Cargo.toml:3: remove it
+"#]];
+    let renderer = Renderer::plain()
+        .short_message(true)
+        .short_message_format(ShortMessageFormat::Gcc);
+    assert_data_eq!(renderer.render(report), expected);
+
+    let expected = str![[r#"
+Cargo.toml(3): warning: unused manifest key
+Cargo.toml(3): remove it
+"#]];
+    let renderer = renderer.short_message_format(ShortMessageFormat::Msvc);
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn markdown() {
+    let source = r#"fn foo(_: u32) {}
+
+fn main() {
+    foo("Bonjour".to_owned());
+}
+"#;
+    let report = &[
+        Level::ERROR
+            .primary_title("mismatched types")
+            .id("E0308")
+            .id_url("https://doc.rust-lang.org/error_codes/E0308.html")
+            .element(
+                Snippet::source(source)
+                    .path("src/main.rs")
+                    .annotation(
+                        AnnotationKind::Primary
+                            .span(39..59)
+                            .label("expected `u32`, found `String`"),
+                    )
+                    .annotation(
+                        AnnotationKind::Context
+                            .span(35..38)
+                            .label("arguments to this function are incorrect"),
+                    ),
+            )
+            .element(Level::NOTE.message("expected type `u32`\n   found struct `String`")),
+        Level::HELP
+            .secondary_title("try removing the method call: `_to_owned`")
+            .element(
+                Snippet::source(source)
+                    .path("src/main.rs")
+                    .patch(Patch::new(48..59, "")),
+            ),
+    ];
+
+    let expected = str![[r#"
+**error[[E0308](<https://doc.rust-lang.org/error_codes/E0308.html>)]: mismatched types**
+
+```text
+ --> src/main.rs:4:9
+  |
+4 |     foo("Bonjour".to_owned());
+  |     --- ^^^^^^^^^^^^^^^^^^^^ expected `u32`, found `String`
+  |     |
+  |     arguments to this function are incorrect
+```
+
+note: expected type `u32`\
+   found struct `String`
+
+**help: try removing the method call: `_to_owned`**
+
+`src/main.rs:4`
+
+```diff
+-    foo("Bonjour".to_owned());
++    foo("Bonjour");
+```
+
+"#]];
+    let renderer = Renderer::styled();
+    assert_data_eq!(renderer.render_markdown(report), expected.raw());
+
+    let expected = str![[r#"
+**error[[E0308](<https://doc.rust-lang.org/error_codes/E0308.html>)]: mismatched types**
+
+```text
+ --> src/main.rs:4:9
+  |
+4 |     foo("Bonjour".to_owned());
+  |     --- ^^^^^^^^^^^^^^^^^^^^ expected `u32`, found `String`
+  |     |
+  |     arguments to this function are incorrect
+```
+
+note: expected type `u32`\
+   found struct `String`
+
+**help: try removing the method call: `_to_owned`**
+
+`src/main.rs:4`
+
+```suggestion
+    foo("Bonjour");
+```
+
+"#]];
+    let renderer = renderer.markdown_suggestion(MarkdownSuggestion::GitHub);
+    assert_data_eq!(renderer.render_markdown(report), expected.raw());
+}
+
+#[test]
+fn markdown_crlf() {
+    let source = "fn main() {\r\n    let x = 1;\r\n}\r\n";
+    let start = source.find('1').unwrap();
+    let report = &[Level::HELP.secondary_title("use a float").element(
+        Snippet::source(source)
+            .path("src/main.rs")
+            .patch(Patch::new(start..start + 1, "1.0")),
+    )];
+
+    // The `\r\n` line endings are kept, so the diff still applies
+    let expected = "**help: use a float**\n\n`src/main.rs:2`\n\n```diff\n-    let x = 1;\r\n+    let x = 1.0;\r\n```\n";
+    let renderer = Renderer::plain();
+    assert_eq!(renderer.render_markdown(report), expected);
+}
+
+#[test]
+fn markdown_escapes_fences() {
+    let source = "/// ```\n/// let x = 1;\n/// ```\nfn f() {}\n";
+    let report = &[Level::WARNING
+        .primary_title("missing `rust` in *code block*")
+        .element(
+            Snippet::source(source)
+                .path("src/lib.rs")
+                .annotation(AnnotationKind::Primary.span(4..7)),
+        )];
+
+    let expected = str![[r#"
+**warning: missing `rust` in \*code block\***
+
+````text
+ --> src/lib.rs:1:5
+  |
+1 | /// ```
+  |     ^^^
+````
+
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render_markdown(report), expected.raw());
+}
+
+#[test]
+fn unified_diff() {
+    let source = "\
+use std::io;
+
+fn one() {}
+fn two() {}
+fn three() {}
+fn four() {}
+fn five() {}
+fn six() {}
+fn seven() {}
+fn eight() {}
+
+fn main() {
+    one();
+}";
+    let snippet = Snippet::source(source)
+        .path("src/main.rs")
+        .patch(Patch::new(0..0, "use std::fmt;\n"))
+        .patch(Patch::new(17..20, "uno"))
+        .patch(Patch::new(131..143, "    uno();\n}"));
+
+    let expected = str![[r#"
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,6 +1,7 @@
++use std::fmt;
+ use std::io;
+ 
+-fn one() {}
++fn uno() {}
+ fn two() {}
+ fn three() {}
+ fn four() {}
+@@ -10,5 +11,5 @@
+ fn eight() {}
+ 
+ fn main() {
+-    one();
++    uno();
+ }
+\ No newline at end of file
+
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render_unified_diff(&snippet), expected.raw());
+
+    let expected = str![[r#"
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -0,0 +1 @@
++use std::fmt;
+@@ -3 +4 @@
+-fn one() {}
++fn uno() {}
+@@ -13 +14 @@
+-    one();
++    uno();
+
+"#]];
+    let renderer = renderer.diff_context_lines(0);
+    assert_data_eq!(renderer.render_unified_diff(&snippet), expected.raw());
+}
+
+#[test]
+fn unified_diff_joined_lines() {
+    let source = "fn main() {\r\n    let x =\r\n        1;\r\n}\r\n";
+    let snippet = Snippet::source(source)
+        .path("src/main.rs")
+        .line_start(10)
+        .patch(Patch::new(24..34, " "));
+
+    let expected = str![[r#"
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -10,4 +10,3 @@
+ fn main() {
+-    let x =
+-        1;
++    let x = 1;
+ }
+
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render_unified_diff(&snippet), expected.raw());
+}
+
+#[test]
+fn unified_diff_matches_apply() {
+    let source = "fn main() {\r\n    let x =\r\n        1;\r\n    dbg!(x);\r\n}\r\n";
+    let snippet = Snippet::source(source)
+        .path("src/main.rs")
+        .patch(Patch::new(24..34, " "))
+        .patch(Patch::new(42..45, "println"))
+        .patch(Patch::new(47..47, "\"{}\", "));
+
+    let patched = MultiFileSuggestion::new()
+        .file(snippet.clone())
+        .apply()
+        .unwrap();
+    assert_eq!(
+        patched,
+        ["fn main() {\r\n    let x = 1;\r\n    println!(\"{}\", x);\r\n}\r\n"]
+    );
+
+    let diff = Renderer::plain().render_unified_diff(&snippet);
+    let from_diff = diff
+        .lines()
+        .skip(3)
+        .filter_map(|line| line.strip_prefix([' ', '+']))
+        .map(|line| format!("{line}\r\n"))
+        .collect::<String>();
+    assert_eq!(from_diff, patched[0]);
+}
+
+#[test]
+fn unified_diff_without_path() {
+    let snippet = Snippet::source("fn main() {}\n").patch(Patch::new(3..7, "start"));
+
+    let renderer = Renderer::plain();
+    assert_data_eq!(
+        renderer.render_unified_diff(&snippet),
+        str![[r#"
+--- a/<anonymous>
++++ b/<anonymous>
+@@ -1 +1 @@
+-fn main() {}
++fn start() {}
+
+"#]]
+    );
+}
+
+#[test]
+fn unified_diff_no_changes() {
+    let snippet = Snippet::source("fn main() {}\n")
+        .path("src/main.rs")
+        .patch(Patch::new(3..7, "main"));
+
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render_unified_diff(&snippet), str![""]);
+}
+
+#[test]
+fn multi_file_suggestion() {
+    let lib = "pub mod a;\n\npub fn f() {}\n";
+    let main = "fn main() {\n    f();\n}\n";
+    let report = &[
+        Level::ERROR
+            .primary_title("cannot find function `f` in this scope")
+            .element(
+                Snippet::source(main)
+                    .path("src/main.rs")
+                    .annotation(AnnotationKind::Primary.span(16..17)),
+            ),
+        Level::HELP
+            .secondary_title("import it from the library")
+            .element(
+                MultiFileSuggestion::new()
+                    .file(
+                        Snippet::source(lib)
+                            .path("src/lib.rs")
+                            .patch(Patch::new(0..0, "pub use a::f;\n")),
+                    )
+                    .file(
+                        Snippet::source(main)
+                            .path("src/main.rs")
+                            .patch(Patch::new(16..17, "lib::f")),
+                    ),
+            ),
+    ];
+
+    let expected_ascii = str![[r#"
+error: cannot find function `f` in this scope
+ --> src/main.rs:2:5
+  |
+2 |     f();
+  |     ^
+  |
+help: import it from the library
+ --> src/lib.rs:1:1
+  |
+1 + pub use a::f;
+  |
+ ::: src/main.rs:2:5
+  |
+2 |     lib::f();
+  |     +++++
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(report), expected_ascii);
+
+    let expected_unicode = str![[r#"
+error: cannot find function `f` in this scope
+  ╭▸ src/main.rs:2:5
+  │
+2 │     f();
+  │     ━
+  ╰╴
+help: import it from the library
+  ╭▸ src/lib.rs:1:1
+  │
+1 + pub use a::f;
+  │
+  ⸬  src/main.rs:2:5
+  │
+2 │     lib::f();
+  ╰╴    +++++
+"#]];
+    let renderer = renderer.decor_style(DecorStyle::Unicode);
+    assert_data_eq!(renderer.render(report), expected_unicode);
+}
+
+#[test]
+fn multi_file_suggestion_apply() {
+    let lib = "pub mod a;\n";
+    let main = "fn main() {\n    f();\n}\n";
+    let suggestion = MultiFileSuggestion::new()
+        .file(
+            Snippet::source(lib)
+                .path("src/lib.rs")
+                .patch(Patch::new(0..0, "pub use a::f;\n")),
+        )
+        .file(
+            Snippet::source(main)
+                .path("src/main.rs")
+                .patch(Patch::new(16..17, "lib::f")),
+        );
+    assert_eq!(
+        suggestion.apply().unwrap(),
+        [
+            "pub use a::f;\npub mod a;\n",
+            "fn main() {\n    lib::f();\n}\n"
+        ]
+    );
+
+    let suggestion = suggestion.file(
+        Snippet::source("é")
+            .path("src/bin.rs")
+            .patch(Patch::new(1..2, "e")),
+    );
+    let error = suggestion.apply().unwrap_err();
+    assert_eq!(error.path(), Some("src/bin.rs"));
+    assert_eq!(error.span(), 1..2);
+    assert_eq!(
+        error.to_string(),
+        "cannot apply patch at 1..2 in `src/bin.rs`: span is not on a `char` boundary"
+    );
+
+    let suggestion = MultiFileSuggestion::new().file(
+        Snippet::source(main)
+            .patch(Patch::new(16..19, "g()"))
+            .patch(Patch::new(17..18, "[]")),
+    );
+    assert_eq!(
+        suggestion.apply().unwrap_err().to_string(),
+        "cannot apply patch at 17..18: span overlaps another patch"
+    );
+}
+
+#[test]
+fn applicability_marker() {
+    let source = "fn main() {\n    let x: &str = String::new();\n}\n";
+    let borrow = Snippet::source(source)
+        .path("src/main.rs")
+        .patch(Patch::new(30..30, "&"))
+        .applicability(Applicability::MachineApplicable);
+    let report = &[
+        Level::ERROR
+            .primary_title("mismatched types")
+            .id("E0308")
+            .element(
+                Snippet::source(source)
+                    .path("src/main.rs")
+                    .annotation(AnnotationKind::Primary.span(30..43)),
+            ),
+        Level::HELP
+            .secondary_title("consider borrowing here")
+            .element(borrow.clone())
+            .element(
+                Snippet::source(source)
+                    .path("src/main.rs")
+                    .patch(Patch::new(23..27, "String"))
+                    .applicability(Applicability::MaybeIncorrect),
+            ),
+    ];
+
+    let expected = str![[r#"
+error[E0308]: mismatched types
+ --> src/main.rs:2:19
+  |
+2 |     let x: &str = String::new();
+  |                   ^^^^^^^^^^^^^
+  |
+help: consider borrowing here
+  |
+2 |     let x: &str = &String::new();
+  |                   +
+  | (maybe incorrect)
+2 -     let x: &str = String::new();
+2 +     let x: String = String::new();
+  |
+"#]];
+    let renderer = Renderer::plain().applicability_marker(true);
+    assert_data_eq!(renderer.render(report), expected);
+
+    let expected = str![[r#"
+**error[E0308]: mismatched types**
+
+```text
+ --> src/main.rs:2:19
+  |
+2 |     let x: &str = String::new();
+  |                   ^^^^^^^^^^^^^
+```
+
+**help: consider borrowing here**
+
+`src/main.rs:2`
+
+```diff
+-    let x: &str = String::new();
++    let x: &str = &String::new();
+```
+
+`src/main.rs:2` (maybe incorrect)
+
+```diff
+-    let x: &str = String::new();
++    let x: String = String::new();
+```
+
+"#]];
+    assert_data_eq!(renderer.render_markdown(report), expected.raw());
+
+    let expected = str![[r#"
+Applicability: MachineApplicable
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,3 +1,3 @@
+ fn main() {
+-    let x: &str = String::new();
++    let x: &str = &String::new();
+ }
+
+"#]];
+    assert_data_eq!(renderer.render_unified_diff(&borrow), expected.raw());
+
+    let expected = str![[r#"
+src/main.rs:2:19: error: mismatched types [E0308]
+src/main.rs:2:19: help: consider borrowing here (maybe incorrect)
+"#]];
+    let renderer = renderer
+        .short_message(true)
+        .short_message_format(ShortMessageFormat::Gcc);
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn applicability_marker_each_suggestion() {
+    let source = "fn main() {\n    let x: &str = String::new();\n}\n";
+    let report = &[Level::HELP
+        .primary_title("consider changing the type")
+        .element(
+            Snippet::source(source)
+                .path("src/main.rs")
+                .patch(Patch::new(23..27, "String"))
+                .applicability(Applicability::MaybeIncorrect),
+        )
+        .element(
+            Snippet::source(source)
+                .path("src/main.rs")
+                .patch(Patch::new(23..27, "/* Type */"))
+                .applicability(Applicability::HasPlaceholders),
+        )];
+
+    let expected = str![[r#"
+help: consider changing the type
+ --> src/main.rs:2:12 (maybe incorrect)
+  |
+2 -     let x: &str = String::new();
+2 +     let x: String = String::new();
+  |
+  | (has placeholders)
+2 -     let x: &str = String::new();
+2 +     let x: /* Type */ = String::new();
+  |
+"#]];
+    let renderer = Renderer::plain().applicability_marker(true);
+    assert_data_eq!(renderer.render(report), expected);
+
+    let expected = str![[r#"
+help: consider changing the type
+  ╭▸ src/main.rs:2:12 (maybe incorrect)
+  │
+2 -     let x: &str = String::new();
+2 +     let x: String = String::new();
+  │
+  ├╴ (has placeholders)
+2 -     let x: &str = String::new();
+2 +     let x: /* Type */ = String::new();
+  ╰╴
+"#]];
+    let renderer = renderer.decor_style(DecorStyle::Unicode);
+    assert_data_eq!(renderer.render(report), expected);
+
+    let expected = str!["help: consider changing the type (maybe incorrect) (has placeholders)"];
+    let renderer = renderer
+        .short_message(true)
+        .short_message_format(ShortMessageFormat::Gcc);
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn suggestion_style() {
+    let source = "fn main() {\n    let x: &str = String::new();\n}\n";
+    let report = |patch, style| {
+        [
+            Level::ERROR
+                .primary_title("mismatched types")
+                .id("E0308")
+                .element(
+                    Snippet::source(source)
+                        .path("src/main.rs")
+                        .annotation(AnnotationKind::Primary.span(30..43)),
+                ),
+            Level::HELP.secondary_title("try").element(
+                Snippet::source(source)
+                    .path("src/main.rs")
+                    .patch(patch)
+                    .suggestion_style(style),
+            ),
+        ]
+    };
+    let borrow = Patch::new(30..43, "&String::new()");
+    let owned = Patch::new(23..27, "String");
+    let renderer = Renderer::plain();
+
+    let expected = str![[r#"
+error[E0308]: mismatched types
+ --> src/main.rs:2:19
+  |
+2 |     let x: &str = String::new();
+  |                   ^^^^^^^^^^^^^
+  |
+help: try
+  |
+2 |     let x: &str = &String::new();
+  |                   +
+"#]];
+    assert_data_eq!(
+        renderer.render(&report(borrow.clone(), SuggestionStyle::Auto)),
+        expected
+    );
+
+    let expected = str![[r#"
+error[E0308]: mismatched types
+ --> src/main.rs:2:19
+  |
+2 |     let x: &str = String::new();
+  |                   ^^^^^^^^^^^^^
+  |
+help: try: `&String::new()`
+"#]];
+    assert_data_eq!(
+        renderer.render(&report(borrow.clone(), SuggestionStyle::Inline)),
+        expected
+    );
+
+    let expected = str![[r#"
+error[E0308]: mismatched types
+ --> src/main.rs:2:19
+  |
+2 |     let x: &str = String::new();
+  |                   ^^^^^^^^^^^^^
+  |
+help: try
+  |
+2 -     let x: &str = String::new();
+2 +     let x: &str = &String::new();
+  |
+"#]];
+    assert_data_eq!(
+        renderer.render(&report(borrow.clone(), SuggestionStyle::Diff)),
+        expected
+    );
+
+    let expected = str![[r#"
+error[E0308]: mismatched types
+ --> src/main.rs:2:19
+  |
+2 |     let x: &str = String::new();
+  |                   ^^^^^^^^^^^^^
+  |
+help: try
+  |
+2 -     let x: &str = String::new();
+2 +     let x: String = String::new();
+  |
+"#]];
+    assert_data_eq!(
+        renderer.render(&report(owned.clone(), SuggestionStyle::Auto)),
+        expected
+    );
+
+    let expected = str![[r#"
+error[E0308]: mismatched types
+ --> src/main.rs:2:19
+  |
+2 |     let x: &str = String::new();
+  |                   ^^^^^^^^^^^^^
+  |
+help: try
+  |
+2 |     let x: String = String::new();
+  |            ~~~~~~
+"#]];
+    assert_data_eq!(
+        renderer.render(&report(owned.clone(), SuggestionStyle::Underline)),
+        expected
+    );
+
+    let expected = str![[r#"
+error[E0308]: mismatched types
+ --> src/main.rs:2:19
+  |
+2 |     let x: &str = String::new();
+  |                   ^^^^^^^^^^^^^
+  |
+help: try
+"#]];
+    assert_data_eq!(
+        renderer.render(&report(borrow.clone(), SuggestionStyle::Hidden)),
+        expected
+    );
+
+    let expected = str![[r#"
+**error[E0308]: mismatched types**
+
+```text
+ --> src/main.rs:2:19
+  |
+2 |     let x: &str = String::new();
+  |                   ^^^^^^^^^^^^^
+```
+
+**help: try**: `&String::new()`
+
+"#]];
+    assert_data_eq!(
+        renderer.render_markdown(&report(borrow.clone(), SuggestionStyle::Inline)),
+        expected
+    );
+}
+
+#[test]
+fn suggestion_style_inline_fallback() {
+    let source = "fn main() {\n    let x = 1;\n}\n";
+    let report = &[
+        Level::ERROR.primary_title("unused variable").element(
+            Snippet::source(source)
+                .path("src/main.rs")
+                .annotation(AnnotationKind::Primary.span(20..21)),
+        ),
+        Level::HELP
+            .secondary_title("prefix it with an underscore")
+            .element(
+                Snippet::source(source)
+                    .path("src/main.rs")
+                    .patch(Patch::new(20..21, "_x"))
+                    .patch(Patch::new(16..19, "let"))
+                    .suggestion_style(SuggestionStyle::Inline),
+            ),
+    ];
+
+    let expected = str![[r#"
+error: unused variable
+ --> src/main.rs:2:9
+  |
+2 |     let x = 1;
+  |         ^
+  |
+help: prefix it with an underscore
+  |
+2 |     let _x = 1;
+  |         +
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn fold_context_lines() {
+    let source = (1..=12)
+        .map(|i| format!("let x{i} = {i};\n"))
+        .collect::<String>();
+    let offset = |line: usize| source.match_indices('\n').nth(line - 2).unwrap().0 + 1;
+    let report = &[Level::ERROR.primary_title("unused variables").element(
+        Snippet::source(&source)
+            .path("src/main.rs")
+            .annotation(AnnotationKind::Primary.span(offset(3) + 4..offset(3) + 6))
+            .annotation(AnnotationKind::Context.span(offset(5) + 4..offset(5) + 6))
+            .annotation(AnnotationKind::Context.span(offset(11) + 4..offset(11) + 6))
+            .context_lines(1, 1),
+    )];
+
+    let expected = str![[r#"
+error: unused variables
+  --> src/main.rs:3:5
+   |
+ 2 | let x2 = 2;
+ 3 | let x3 = 3;
+   |     ^^
+ 4 | let x4 = 4;
+ 5 | let x5 = 5;
+   |     --
+ 6 | let x6 = 6;
+...
+10 | let x10 = 10;
+11 | let x11 = 11;
+   |     --
+12 | let x12 = 12;
+   |
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn multiline_language_profile() {
+    let source = r#"def main():
+    total = sum(
+        x
+        for x in range(10)
+        # only even numbers
+        if x % 2 == 0
+    )
+    return total
+"#;
+    let snippet = Snippet::source(source)
+        .path("main.py")
+        .annotation(AnnotationKind::Primary.span(24..121).label("generator"));
+
+    let expected = str![[r#"
+error: expected `int`
+ --> main.py:2:13
+  |
+2 |       total = sum(
+  |  _____________^
+3 | |         x
+4 | |         for x in range(10)
+5 | |         # only even numbers
+6 | |         if x % 2 == 0
+7 | |     )
+  | |_____^ generator
+"#]];
+    let renderer = Renderer::plain();
+    let report = &[Level::ERROR
+        .primary_title("expected `int`")
+        .element(snippet.clone())];
+    assert_data_eq!(renderer.render(report), expected);
+
+    let python = LineComments::new(&["#"]).delimiters(&["(", ")", "[", "]", "{", "}"]);
+    let expected = str![[r#"
+error: expected `int`
+ --> main.py:2:13
+  |
+2 |       total = sum(
+  |  _____________^
+3 | |         x
+4 | |         for x in range(10)
+... |
+7 | |     )
+  | |_____^ generator
+"#]];
+    let report = &[Level::ERROR
+        .primary_title("expected `int`")
+        .element(snippet.clone().language(&python))];
+    assert_data_eq!(renderer.render(report), expected);
+
+    let expected = str![[r#"
+error: expected `int`
+ --> main.py:2:13
+  |
+2 |       total = sum(
+  |  _____________^
+3 | |         x
+... |
+6 | |         if x % 2 == 0
+7 | |     )
+  | |_____^ generator
+"#]];
+    let report = &[Level::ERROR
+        .primary_title("expected `int`")
+        .element(snippet.language(&python).multiline_lines(2))];
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn elided_line_count() {
+    let source = (1..=12)
+        .map(|i| format!("let x{i} = {i};\n"))
+        .collect::<String>();
+    let offset = |line: usize| source.match_indices('\n').nth(line - 2).unwrap().0 + 1;
+    let report = &[Level::ERROR.primary_title("unused variables").element(
+        Snippet::source(&source)
+            .path("src/main.rs")
+            .annotation(AnnotationKind::Primary.span(offset(2) + 4..offset(11) + 6)),
+    )];
+
+    let expected = str![[r#"
+error: unused variables
+  --> src/main.rs:2:5
+   |
+ 2 |   let x2 = 2;
+   |  _____^
+ 3 | | let x3 = 3;
+ 4 | | let x4 = 4;
+ 5 | | let x5 = 5;
+...  | 4 lines omitted
+10 | | let x10 = 10;
+11 | | let x11 = 11;
+   | |______^
+"#]];
+    let renderer = Renderer::plain().elided_line_count(true);
+    assert_data_eq!(renderer.render(report), expected);
+
+    let expected = str![[r#"
+error: unused variables
+   ╭▸ src/main.rs:2:5
+   │
+ 2 │   let x2 = 2;
+   │ ┏━━━━━┛
+ 3 │ ┃ let x3 = 3;
+ 4 │ ┃ let x4 = 4;
+ 5 │ ┃ let x5 = 5;
+   ┆ ┇ 4 lines omitted
+10 │ ┃ let x10 = 10;
+11 │ ┃ let x11 = 11;
+   ╰╴┗━━━━━━┛
+"#]];
+    let renderer = renderer.decor_style(DecorStyle::Unicode);
+    assert_data_eq!(renderer.render(report), expected);
+
+    let report = &[Level::HELP.primary_title("rename the variables").element(
+        Snippet::source(&source)
+            .path("src/main.rs")
+            .patch(Patch::new(offset(2) + 4..offset(2) + 6, "_x2"))
+            .patch(Patch::new(offset(11) + 4..offset(11) + 7, "_x11")),
+    )];
+
+    let expected = str![[r#"
+help: rename the variables
+  --> src/main.rs:2:5
+   |
+ 2 ~ let _x2 = 2;
+ 3 | let x3 = 3;
+...  6 lines omitted
+10 | let x10 = 10;
+11 ~ let _x11 = 11;
+   |
+"#]];
+    let renderer = Renderer::plain().elided_line_count(true);
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn max_lines() {
+    let source = (1..=12)
+        .map(|i| format!("let x{i} = {i};\n"))
+        .collect::<String>();
+    let offset = |line: usize| source.match_indices('\n').nth(line - 2).unwrap().0 + 1;
+    let snippet = (2..=12).fold(
+        Snippet::source(&source).path("src/main.rs"),
+        |snippet, line| {
+            snippet.annotation(AnnotationKind::Context.span(offset(line) + 4..offset(line) + 6))
+        },
+    );
+    let report = &[Level::ERROR
+        .primary_title("unused variables")
+        .element(snippet.clone().max_lines(3))];
+
+    let expected = str![[r#"
+error: unused variables
+ --> src/main.rs:2:5
+  |
+2 | let x2 = 2;
+  |     --
+3 | let x3 = 3;
+  |     --
+4 | let x4 = 4;
+  |     --
+... 8 more lines omitted
+  |
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(report), expected);
+
+    let expected = str![[r#"
+error: unused variables
+  ╭▸ src/main.rs:2:5
+  │
+2 │ let x2 = 2;
+  │     ──
+3 │ let x3 = 3;
+  │     ──
+4 │ let x4 = 4;
+  │     ──
+  ┆ 8 more lines omitted
+  ╰╴
+"#]];
+    let renderer = renderer.decor_style(DecorStyle::Unicode);
+    assert_data_eq!(renderer.render(report), expected);
+
+    let report = &[Level::ERROR
+        .primary_title("unused variables")
+        .element(snippet.max_lines(0))];
+
+    // Only the summary
+    let expected = str![[r#"
+error: unused variables
+ --> src/main.rs
+  |
+... 11 more lines omitted
+  |
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn large_source_annotations_near_end() {
+    let source = (1..=1_000_000)
+        .map(|i| format!("entry {i}\n"))
+        .collect::<String>();
+    let span = |line: usize| {
+        let start = source.find(&format!("entry {line}\n")).unwrap();
+        start..start + 5
+    };
+    let report = &[Level::ERROR.primary_title("unexpected entries").element(
+        Snippet::source(&source)
+            .path("huge.log")
+            .annotation(AnnotationKind::Context.span(span(999_990)))
+            .annotation(AnnotationKind::Primary.span(span(999_998)).label("here"))
+            .annotation(AnnotationKind::Context.span(span(1_000_000))),
+    )];
+
+    let expected = str![[r#"
+error: unexpected entries
+       --> huge.log:999998:1
+        |
+ 999990 | entry 999990
+        | -----
+...
+ 999998 | entry 999998
+        | ^^^^^ here
+ 999999 | entry 999999
+1000000 | entry 1000000
+        | -----
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn excerpt() {
+    // Long enough for line numbers to be resumed from a remembered line start
+    let source = (1..=200_000)
+        .map(|i| format!("entry {i}\n"))
+        .collect::<String>();
+    let span = |line: usize| {
+        let start = source.find(&format!("entry {line}\n")).unwrap();
+        start..start + 5
+    };
+    let mut file = FileSource::new(Cursor::new(source.as_bytes()));
+    assert_eq!(file.line_count().unwrap(), 200_000);
+    let excerpt = file
+        .excerpt([span(199_990), span(3), span(5), span(150_000)])
+        .unwrap();
+    let report = &[Level::ERROR.primary_title("unexpected entries").element(
+        Snippet::excerpt(excerpt)
+            .path("huge.log")
+            .annotation(AnnotationKind::Primary.span(span(3)))
+            .annotation(AnnotationKind::Context.span(span(5)))
+            .annotation(AnnotationKind::Context.span(span(150_000)))
+            .annotation(AnnotationKind::Context.span(span(199_990))),
+    )];
+
+    let expected = str![[r#"
+error: unexpected entries
+      --> huge.log:3:1
+       |
+     3 | entry 3
+       | ^^^^^
+     4 | entry 4
+     5 | entry 5
+       | -----
+...
+150000 | entry 150000
+       | -----
+...
+199990 | entry 199990
+       | -----
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(report), expected);
+
+    let full = &[Level::ERROR.primary_title("unexpected entries").element(
+        Snippet::source(&source)
+            .path("huge.log")
+            .annotation(AnnotationKind::Primary.span(span(3)))
+            .annotation(AnnotationKind::Context.span(span(5)))
+            .annotation(AnnotationKind::Context.span(span(150_000)))
+            .annotation(AnnotationKind::Context.span(span(199_990))),
+    )];
+    assert_eq!(renderer.render(report), renderer.render(full));
+}
+
+#[test]
+fn excerpt_annotations_outside() {
+    let source = (1..=20).map(|i| format!("entry {i}\n")).collect::<String>();
+    let span = |line: usize| {
+        let start = source.find(&format!("entry {line}\n")).unwrap();
+        start..start + 5
+    };
+    let mut file = FileSource::new(Cursor::new(source.as_bytes()));
+    let excerpt = file.excerpt([span(3), span(10)]).unwrap();
+    let report = &[Level::ERROR.primary_title("unexpected entries").element(
+        Snippet::excerpt(excerpt)
+            .path("small.log")
+            .annotation(AnnotationKind::Primary.span(span(3)))
+            // Not read
+            .annotation(AnnotationKind::Context.span(span(7)).label("skipped"))
+            .annotation(AnnotationKind::Context.span(span(20)).label("past the end"))
+            // Across the lines that were not read
+            .annotation(
+                AnnotationKind::Context
+                    .span(span(3).start..span(10).end)
+                    .label("across"),
+            ),
+    )];
+
+    let expected = str![[r#"
+error: unexpected entries
+  --> small.log:3:1
+   |
+ 3 |   entry 3
+   |  _-^^^^
+...  |
+10 | | entry 10
+   | |_____- across
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(report), expected);
+}
+
+#[test]
+fn excerpt_insertion_at_eof() {
+    let source = (1..=20)
+        .map(|i| format!("entry {i}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut file = FileSource::new(Cursor::new(source.as_bytes()));
+    let eof = source.len();
+    let excerpt = file.excerpt(Some(eof..eof)).unwrap();
+    let report = &[Level::ERROR.primary_title("missing trailer").element(
+        Snippet::excerpt(excerpt)
+            .path("small.log")
+            .annotation(AnnotationKind::Primary.insertion(eof)),
+    )];
+    let full = &[Level::ERROR.primary_title("missing trailer").element(
+        Snippet::source(&source)
+            .path("small.log")
+            .annotation(AnnotationKind::Primary.insertion(eof)),
+    )];
+
+    let expected = str![[r#"
+error: missing trailer
+  --> small.log:20:9
+   |
+20 | entry 20 <EOF>
+   |          ^^^^^
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(report), expected);
+    assert_eq!(renderer.render(report), renderer.render(full));
+}
+
+#[test]
+fn grapheme_clusters_trim_combining_mark() {
+    let source = format!("let s = \"{}\"; let t = s + \"!\";", "࿅࿆࿇".repeat(5));
+    let plus = source.find('+').unwrap();
+    let s_ = source.find("s +").unwrap();
+    let input = &[Level::ERROR
+        .primary_title("cannot add `&str` to `&str`")
+        .element(
+            Snippet::source(&source)
+                .path("src/main.rs")
+                .annotation(AnnotationKind::Primary.span(plus..plus + 1))
+                .annotation(AnnotationKind::Context.span(s_..s_ + 1).label("&str")),
+        )];
+
+    // The combining `࿆` is left on its own, like rustc
+    let expected = str![[r#"
+error: cannot add `&str` to `&str`
+ --> src/main.rs:1:38
+  |
+1 | ...࿆࿇"; let t = s + "!";
+  |                - ^
+  |                |
+  |                &str
+"#]];
+    let renderer = Renderer::plain().term_width(40);
+    assert_data_eq!(renderer.render(input), expected);
+
+    // `࿆` is trimmed with the `࿅` it combines with
+    let expected = str![[r#"
+error: cannot add `&str` to `&str`
+ --> src/main.rs:1:38
+  |
+1 | ...࿇"; let t = s + "!";
+  |                - ^
+  |                |
+  |                &str
+"#]];
+    let renderer = renderer.grapheme_clusters(true);
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn grapheme_clusters() {
+    let source = "let family = \"👨‍👩‍👧\"; let flag = \"🇳🇴\"; let cafe = \"cafe\u{301}\";";
+    let family = source.find('👨').unwrap();
+    let flag = source.find('🇳').unwrap();
+    let accent = source.find('\u{301}').unwrap();
+    let input = &[Level::ERROR.primary_title("grapheme clusters").element(
+        Snippet::source(source)
+            .path("src/main.rs")
+            // Only the first emoji of the family
+            .annotation(
+                AnnotationKind::Primary
+                    .span(family..family + '👨'.len_utf8())
+                    .label("family"),
+            )
+            // Only the first regional indicator of the flag
+            .annotation(AnnotationKind::Context.span(flag..flag + 4).label("flag"))
+            // Only the combining accent
+            .annotation(
+                AnnotationKind::Context
+                    .span(accent..accent + 2)
+                    .label("accent"),
+            ),
+    )];
+
+    // Each `char` on its own, with ZWJ removed, like rustc
+    let expected = str![[r#"
+error: grapheme clusters
+ --> src/main.rs:1:15
+  |
+1 | let family = "👨👩👧"; let flag = "🇳🇴"; let cafe = "café";
+  |               ^^ family            - flag               - accent
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+error: grapheme clusters
+ --> src/main.rs:1:15
+  |
+1 | ...= "👨👩👧"; let flag = "🇳🇴"; let cafe = "café";
+  |       ^^ family            - flag               - accent
+"#]];
+    let renderer = renderer.term_width(40);
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+error: grapheme clusters
+ --> src/main.rs:1:15
+  |
+1 | let family = "👨‍👩‍👧"; let flag = "🇳🇴"; let cafe = "café";
+  |               ^^ family        -- flag             - accent
+"#]];
+    let renderer = Renderer::plain().grapheme_clusters(true);
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+error: grapheme clusters
+ --> src/main.rs:1:15
+  |
+1 | ...= "👨‍👩‍👧"; let flag = "🇳🇴"; let cafe = "café";
+  |       ^^ family        -- flag             - accent
+"#]];
+    let renderer = renderer.term_width(40);
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn ambiguous_width() {
+    let source = r#"let s = "①②③"; let α = s + "■";"#;
+    let alpha = source.find('α').unwrap();
+    let plus = source.find('+').unwrap();
+    let input = &[
+        Group::with_title(Level::ERROR.primary_title("cannot add `&str` to `&str`")).element(
+            Snippet::source(source)
+                .path("src/main.rs")
+                .annotation(
+                    AnnotationKind::Primary
+                        .span(plus..plus + 1)
+                        .label("`+` here"),
+                )
+                .annotation(
+                    AnnotationKind::Context
+                        .span(alpha..alpha + 'α'.len_utf8())
+                        .label("α is a `&str`"),
+                ),
+        ),
+        Group::with_title(Level::HELP.secondary_title("create an owned `String`")).element(
+            Snippet::source(source)
+                .path("src/main.rs")
+                .patch(Patch::new(plus - 1..plus - 1, ".to_owned()")),
+        ),
+    ];
+
+    let expected = str![[r#"
+error: cannot add `&str` to `&str`
+ --> src/main.rs:1:26
+  |
+1 | let s = "①②③"; let α = s + "■";
+  |                    -     ^ `+` here
+  |                    |
+  |                    α is a `&str`
+  |
+help: create an owned `String`
+  |
+1 | let s = "①②③"; let α = s.to_owned() + "■";
+  |                         +++++++++++
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+error: cannot add `&str` to `&str`
+ --> src/main.rs:1:26
+  |
+1 | let s = "①②③"; let α = s + "■";
+  |                       --     ^ `+` here
+  |                       |
+  |                       α is a `&str`
+  |
+help: create an owned `String`
+  |
+1 | let s = "①②③"; let α = s.to_owned() + "■";
+  |                             +++++++++++
+"#]];
+    let renderer = renderer.ambiguous_width(AmbiguousWidth::Wide);
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn ambiguous_width_greek() {
+    let source = "let π = αβγ + 1;";
+    let start = source.find('α').unwrap();
+    let end = source.find(" +").unwrap();
+    let input = &[Level::ERROR
+        .primary_title("cannot find value `αβγ` in this scope")
+        .element(
+            Snippet::source(source)
+                .path("src/main.rs")
+                .annotation(AnnotationKind::Primary.span(start..end)),
+        )];
+
+    let expected = str![[r#"
+error: cannot find value `αβγ` in this scope
+ --> src/main.rs:1:9
+  |
+1 | let π = αβγ + 1;
+  |          ^^^^^^
+"#]];
+    let renderer = Renderer::plain().ambiguous_width(AmbiguousWidth::Wide);
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn binary_lossy() {
+    let bytes = b"name = \"caf\xe9\"\nvalue = \"\xe2\x82\"\n";
+    let input = &[Level::ERROR.primary_title("invalid UTF-8").element(
+        Snippet::binary(bytes, BinaryView::Lossy)
+            .path("config.toml")
+            .annotation(
+                AnnotationKind::Primary
+                    .span(11..12)
+                    .label("Latin-1 encoded `é`"),
+            )
+            // Only the second byte of a truncated `€`
+            .annotation(AnnotationKind::Context.span(24..25).label("truncated `€`")),
+    )];
+
+    let expected = str![[r#"
+error: invalid UTF-8
+ --> config.toml:1:12
+  |
+1 | name = "caf�"
+  |            ^ Latin-1 encoded `é`
+2 | value = "�"
+  |          - truncated `€`
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn binary_hexdump() {
+    let mut bytes = b"\x7fELF\x02\x01\x01\x00".to_vec();
+    bytes.extend([0; 8]);
+    bytes.extend(b"\x03\x00\x3e\x00\x01\x00\x00\x00");
+    bytes.extend(b"\x40\x10\x00\x00\x00\x00\x00\x00hello, world!\n");
+    let input = &[Level::ERROR.primary_title("invalid ELF header").element(
+        Snippet::binary(&bytes, BinaryView::Hexdump)
+            .path("a.out")
+            .annotation(AnnotationKind::Primary.span(6..7).label("unknown version"))
+            .annotation(
+                AnnotationKind::Context
+                    .span(28..36)
+                    .label("entry point past the end"),
+            )
+            .annotation(
+                AnnotationKind::Context
+                    .span(bytes.len()..bytes.len())
+                    .label("end of file"),
+            ),
+    )];
+
+    let expected = str![[r#"
+error: invalid ELF header
+--> a.out
+ |
+ | 00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|
+ |                             ^^ unknown version                     ^
+ | 00000010  03 00 3e 00 01 00 00 00  40 10 00 00 00 00 00 00  |..>.....@.......|
+ |                                                -----------               ----
+ |                                                |
+ |                                                entry point past the end
+ | 00000020  68 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a        |hello, world!.|
+ |           -----------                                -       ----
+ |                                                      |
+ |                                                      end of file
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn line_labels() {
+    const CELL: Prefixed<'_> = Prefixed::new("In[3]:");
+    let cell = "import numpy as np\n\nx = np.arange(10)\ny = x.reshape(3, 3)\nprint(y)\n";
+    let lib = "def reshape(a, newshape):\n    ...\n";
+    let input = &[
+        Group::with_title(
+            Level::ERROR.primary_title("cannot reshape array of size 10 into shape (3,3)"),
+        )
+        .element(
+            Snippet::source(cell)
+                .line_labels(&CELL)
+                .annotation(AnnotationKind::Context.span(4..9).label("imported here"))
+                .annotation(
+                    AnnotationKind::Primary
+                        .span(cell.find("x.reshape").unwrap()..cell.find("\nprint").unwrap())
+                        .label("10 elements do not fit 9"),
+                ),
+        ),
+        Group::with_title(Level::NOTE.secondary_title("called here")).element(
+            Snippet::source(lib)
+                .path("numpy/core/fromnumeric.py")
+                .line_start(196)
+                .annotation(AnnotationKind::Context.span(4..11)),
+        ),
+        Group::with_title(Level::HELP.secondary_title("use an array of size 9")).element(
+            Snippet::source(cell).line_labels(&CELL).patch(Patch::new(
+                cell.find("10").unwrap()..cell.find("10").unwrap() + 2,
+                "9",
+            )),
+        ),
+    ];
+
+    let expected = str![[r#"
+error: cannot reshape array of size 10 into shape (3,3)
+        |
+In[3]:1 | import numpy as np
+        |     ----- imported here
+...
+In[3]:4 | y = x.reshape(3, 3)
+        |     ^^^^^^^^^^^^^^^ 10 elements do not fit 9
+        |
+note: called here
+       --> numpy/core/fromnumeric.py:196:5
+        |
+    196 | def reshape(a, newshape):
+        |     -------
+help: use an array of size 9
+        |
+In[3]:3 - x = np.arange(10)
+In[3]:3 + x = np.arange(9)
+        |
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn soft_wrap() {
+    let source = r#"function f(){var a=document.getElementById("app");var b=a.querySelectorAll(".item");for(var i=0;i<b.length;i++){b[i].addEventListener("click",function(e){e.preventDefault();undefinedFunction(e.target)})}}
+f();
+"#;
+    let call = source.find("undefinedFunction").unwrap();
+    let listener = source.find("b[i].addEventListener").unwrap();
+    let input = &[Level::ERROR
+        .primary_title("`undefinedFunction` is not defined")
+        .element(
+            Snippet::source(source)
+                .path("app.min.js")
+                .annotation(
+                    AnnotationKind::Primary
+                        .span(call..call + "undefinedFunction".len())
+                        .label("not defined"),
+                )
+                .annotation(
+                    AnnotationKind::Context
+                        .span(listener..call - 1)
+                        .label("in this listener"),
+                ),
+        )];
+
+    let expected = str![[r#"
+error: `undefinedFunction` is not defined
+ --> app.min.js:1:174
+  |
+1 | function f(){var a=document.getElementById("app");var b=
+> | a.querySelectorAll(".item");for(var i=0;i<b.length;i++){
+> | b[i].addEventListener("click",function(e){e.preventDefau
+  | --------------------------------------------------------
+> | lt();undefinedFunction(e.target)})}}
+  | ---- ^^^^^^^^^^^^^^^^^ not defined
+  | |
+  | in this listener
+"#]];
+    let renderer = Renderer::plain().term_width(60).soft_wrap(true);
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+error: `undefinedFunction` is not defined
+  ╭▸ app.min.js:1:174
+  │
+1 │ function f(){var a=document.getElementById("app");var b=
+↪ │ a.querySelectorAll(".item");for(var i=0;i<b.length;i++){
+↪ │ b[i].addEventListener("click",function(e){e.preventDefau
+  │ ────────────────────────────────────────────────────────
+↪ │ lt();undefinedFunction(e.target)})}}
+  │ ┬─── ━━━━━━━━━━━━━━━━━ not defined
+  │ │
+  ╰╴in this listener
+"#]];
+    let renderer = renderer.decor_style(DecorStyle::Unicode);
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn trim() {
+    let source = r#"                                SELECT id, name, email, created_at, updated_at FROM users WHERE emial = 'someone@example.com' ORDER BY created_at DESC"#;
+    let column = source.find("emial").unwrap();
+    let input = &[Level::ERROR
+        .primary_title("unknown column `emial`")
+        .element(
+            Snippet::source(source).path("query.sql").annotation(
+                AnnotationKind::Primary
+                    .span(column..column + 5)
+                    .label("did you mean `email`?"),
+            ),
+        )];
+
+    let expected = str![[r#"
+error: unknown column `emial`
+ --> query.sql:1:97
+  |
+1 | ...sers WHERE emial = 'someone@example.com' ORDER BY ...
+  |               ^^^^^ did you mean `email`?
+"#]];
+    let renderer = Renderer::plain().term_width(60);
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+error: unknown column `emial`
+ --> query.sql:1:97
+  |
+1 | ..._at FROM users WHERE emial = 'someone@example.com'...
+  |                         ^^^^^ did you mean `email`?
+"#]];
+    let renderer = renderer.trim(Trim::new().span_padding(20));
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+error: unknown column `emial`
+ --> query.sql:1:97
+  |
+1 |                                 SELECT id, name, email, created_at, updated_at FROM users WHERE emial = 'someone@example.com' ...
+  |                                                                                                 ^^^^^ did you mean `email`?
+"#]];
+    let renderer = renderer.trim(Trim::new().mode(TrimMode::KeepLeft));
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+error: unknown column `emial`
+ --> query.sql:1:97
+  |
+1 |                                 SELECT id, name, email, created_at, updated_at FROM users WHERE emial = 'someone@example.com' ORDER BY created_at DESC
+  |                                                                                                 ^^^^^ did you mean `email`?
+"#]];
+    let renderer = renderer.trim(Trim::new().mode(TrimMode::Never));
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn trim_long_whitespace() {
+    let source = format!("{}return emial;", " ".repeat(40));
+    let column = source.find("emial").unwrap();
+    let input = &[Level::ERROR
+        .primary_title("cannot find value `emial` in this scope")
+        .element(
+            Snippet::source(&source)
+                .path("src/lib.rs")
+                .annotation(AnnotationKind::Primary.span(column..column + 5)),
+        )];
+
+    // 22 columns of the indentation are kept, the first 3 as `...`
+    let expected = str![[r#"
+error: cannot find value `emial` in this scope
+ --> src/lib.rs:1:48
+  |
+1 | ...                   return emial;
+  |                              ^^^^^
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+error: cannot find value `emial` in this scope
+ --> src/lib.rs:1:48
+  |
+1 | ... return emial;
+  |            ^^^^^
+"#]];
+    let renderer = renderer.trim(Trim::new().long_whitespace(8, 4));
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+error: cannot find value `emial` in this scope
+ --> src/lib.rs:1:48
+  |
+1 |                                         return emial;
+  |                                                ^^^^^
+"#]];
+    let renderer = renderer.trim(Trim::new().long_whitespace(40, 4));
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn trim_new_matches_default() {
+    let render = |renderer: &Renderer, indent: usize| {
+        let source = format!("{}return emial;", " ".repeat(indent));
+        let column = source.find("emial").unwrap();
+        let input = &[Level::ERROR
+            .primary_title("cannot find value `emial` in this scope")
+            .element(
+                Snippet::source(&source)
+                    .path("src/lib.rs")
+                    .annotation(AnnotationKind::Primary.span(column..column + 5)),
+            )];
+        renderer.render(input)
+    };
+
+    let renderer = Renderer::plain();
+    let trim = Renderer::plain().trim(Trim::new());
+    for indent in 0..60 {
+        assert_eq!(render(&trim, indent), render(&renderer, indent));
+    }
+
+    // Long whitespace starts past 26 columns, as before trimming was configurable
+    let expected = str![[r#"
+error: cannot find value `emial` in this scope
+ --> src/lib.rs:1:34
+  |
+1 |                           return emial;
+  |                                  ^^^^^
+"#]];
+    assert_data_eq!(render(&trim, 26), expected);
+    let expected = str![[r#"
+error: cannot find value `emial` in this scope
+ --> src/lib.rs:1:35
+  |
+1 | ...                   return emial;
+  |                              ^^^^^
+"#]];
+    assert_data_eq!(render(&trim, 27), expected);
+}
+
+#[test]
+fn visible_whitespace() {
+    let source = "fn main() {\r\n\tlet x = 1;  \r\n    x\r\n}\r\n";
+    let trailing = source.find(";  ").unwrap() + 1;
+    let tab = source.find('\t').unwrap();
+    let crlf = source.find("x\r\n").unwrap() + 1;
+    let input = &[Level::WARNING
+        .primary_title("inconsistent whitespace")
+        .element(
+            Snippet::source(source)
+                .path("main.rs")
+                .annotation(
+                    AnnotationKind::Primary
+                        .span(trailing..trailing + 2)
+                        .label("trailing whitespace"),
+                )
+                .annotation(
+                    AnnotationKind::Context
+                        .span(tab..tab + 1)
+                        .label("indented with a tab"),
+                )
+                .annotation(
+                    AnnotationKind::Context
+                        .span(crlf..crlf + 1)
+                        .label("CRLF line ending"),
+                )
+                .visible_whitespace(true),
+        )];
+
+    let expected = str![[r#"
+warning: inconsistent whitespace
+ --> main.rs:2:12
+  |
+2 | →   let·x·=·1;··␍
+  | ----          ^^ trailing whitespace
+  | |
+  | indented with a tab
+3 | ····x␍
+  |      - CRLF line ending
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected);
+
+    let expected = str![[r#"
+warning: inconsistent whitespace
+ --> main.rs:2:12
+  |
+2 | >   let.x.=.1;..␍
+  | ----          ^^ trailing whitespace
+  | |
+  | indented with a tab
+3 | ....x␍
+  |      - CRLF line ending
+"#]];
+    let renderer = renderer.ambiguous_width(AmbiguousWidth::Wide);
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn crlf_suggestion_multiline_removal() {
+    let source =
+        "fn main() {\r\n    let a = 1;\r\n    let b = 2;\r\n    let c = 3;\r\n    foo(a);\r\n}\r\n";
+    let start = source.find("    let b").unwrap();
+    let end = source.find("    foo").unwrap();
+    let snippet = Snippet::source(source)
+        .path("src/main.rs")
+        .patch(Patch::new(start..end, ""));
+    let input = &[Level::HELP
+        .secondary_title("remove the unused variables")
+        .element(snippet.clone())];
+
+    let expected = str![[r#"
+help: remove the unused variables
+ --> src/main.rs:3:1
+  |
+3 -     let b = 2;
+4 -     let c = 3;
+  |
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected);
+
+    let input = &[Level::HELP
+        .secondary_title("remove the unused variables")
+        .element(snippet.fold(false))];
+    let expected = str![[r#"
+help: remove the unused variables
+ --> src/main.rs:3:1
+  |
+1 | fn main() {
+2 |     let a = 1;
+3 |     foo(a);
+4 | }
+  |
+"#]];
+    assert_data_eq!(renderer.render(input), expected);
+
+    let patched = MultiFileSuggestion::new()
+        .file(
+            Snippet::source(source)
+                .path("src/main.rs")
+                .patch(Patch::new(start..end, "")),
+        )
+        .apply()
+        .unwrap();
+    assert_eq!(
+        patched,
+        ["fn main() {\r\n    let a = 1;\r\n    foo(a);\r\n}\r\n"]
+    );
+}
+
+#[test]
+fn crlf_suggestion_multiline_addition() {
+    let source = "fn main() {\r\n    let a = 1;\r\n    foo(a, b, c);\r\n}\r\n";
+    let at = source.find("    foo").unwrap();
+    let snippet = Snippet::source(source)
+        .path("src/main.rs")
+        .patch(Patch::new(at..at, "    let b = 2;\r\n    let c = 3;\r\n"));
+    let input = &[Level::HELP
+        .secondary_title("declare the missing variables")
+        .element(snippet.clone())];
+
+    let expected = str![[r#"
+help: declare the missing variables
+ --> src/main.rs:3:1
+  |
+3 +     let b = 2;
+4 +     let c = 3;
+  |
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected);
+
+    let input = &[Level::HELP
+        .secondary_title("declare the missing variables")
+        .element(snippet.clone().fold(false))];
+    let expected = str![[r#"
+help: declare the missing variables
+ --> src/main.rs:3:1
+  |
+1 | fn main() {
+2 |     let a = 1;
+3 +     let b = 2;
+4 +     let c = 3;
+5 |     foo(a, b, c);
+6 | }
+  |
+"#]];
+    assert_data_eq!(renderer.render(input), expected);
+
+    let patched = MultiFileSuggestion::new().file(snippet).apply().unwrap();
+    assert_eq!(
+        patched,
+        [
+            "fn main() {\r\n    let a = 1;\r\n    let b = 2;\r\n    let c = 3;\r\n    foo(a, b, c);\r\n}\r\n"
+        ]
+    );
+}
+
+#[test]
+fn insertion_point() {
+    let source = "fn main() {\n    let x = 1\n    foo(x,\n";
+    let semi = source.find(" 1").unwrap() + 2;
+    let input = &[
+        Level::ERROR
+            .primary_title("expected `;`, found `foo`")
+            .element(
+                Snippet::source(source)
+                    .path("src/main.rs")
+                    .annotation(
+                        AnnotationKind::Primary
+                            .insertion(semi)
+                            .label("expected `;`"),
+                    )
+                    .annotation(AnnotationKind::Context.span(semi + 5..semi + 8)),
+            ),
+        Level::ERROR
+            .primary_title("this file contains an unclosed delimiter")
+            .element(
+                Snippet::source(source)
+                    .path("src/main.rs")
+                    .annotation(
+                        AnnotationKind::Context
+                            .span(10..11)
+                            .label("unclosed delimiter"),
+                    )
+                    .annotation(AnnotationKind::Primary.insertion(source.len())),
+            ),
+    ];
+
+    let expected = str![[r#"
+error: expected `;`, found `foo`
+ --> src/main.rs:2:14
+  |
+2 |     let x = 1
+  |             /\ expected `;`
+3 |     foo(x,
+  |     ---
+  |
+error: this file contains an unclosed delimiter
+ --> src/main.rs:3:12
+  |
+1 | fn main() {
+  |           - unclosed delimiter
+2 |     let x = 1
+3 |     foo(x, <EOF>
+  |            ^^^^^
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected.raw());
+
+    let expected = str![[r#"
+error: expected `;`, found `foo`
+  ╭▸ src/main.rs:2:14
+  │
+2 │     let x = 1
+  │             ╱╲ expected `;`
+3 │     foo(x,
+  │     ───
+  ╰╴
+error: this file contains an unclosed delimiter
+  ╭▸ src/main.rs:3:12
+  │
+1 │ fn main() {
+  │           ─ unclosed delimiter
+2 │     let x = 1
+3 │     foo(x, <EOF>
+  ╰╴           ━━━━━
+"#]];
+    let renderer = renderer.decor_style(DecorStyle::Unicode);
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn reveal_invisible() {
+    let source = "fn main() {\n    let access_level = \"user\";\n    if access_level != \"user\u{202e} \u{2066}// Check if admin\u{2069} \u{2066}\" {\n        println!(\"You are an admin.\");\n    }\n}\n";
+    let condition = source.find("access_level !=").unwrap();
+    let input = &[Level::WARNING
+        .primary_title("this comparison is always true")
+        .element(
+            Snippet::source(source)
+                .path("src/main.rs")
+                .annotation(AnnotationKind::Primary.span(condition..condition + 12))
+                .reveal_invisible(true),
+        )];
+
+    let expected = str![[r#"
+warning: this comparison is always true
+ --> src/main.rs:3:8
+  |
+3 |     if access_level != "user� �// Check if admin� �" {
+  |        ^^^^^^^^^^^^         - -                 - - U+2066 LEFT-TO-RIGHT ISOLATE
+  |                             | |                 |
+  |                             | |                 U+2069 POP DIRECTIONAL ISOLATE
+  |                             | U+2066 LEFT-TO-RIGHT ISOLATE
+  |                             U+202E RIGHT-TO-LEFT OVERRIDE
+"#]];
+    let renderer = Renderer::plain();
+    assert_data_eq!(renderer.render(input), expected);
+}
+
+#[test]
+fn render_styled() {
+    let source = "let x = 1;";
+    let input = &[Level::WARNING
+        .primary_title("unused variable: `x`")
+        .element(
+            Snippet::source(source)
+                .path("src/lib.rs")
+                .annotation(AnnotationKind::Primary.span(4..5).label("unused")),
+        )];
+
+    let renderer = Renderer::plain();
+    let groups = renderer.render_styled(input);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].severity, Severity::Warning);
+
+    // The same text as `render`
+    let text = groups[0]
+        .lines
+        .iter()
+        .map(|line| line.iter().map(|run| run.text.as_str()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert_eq!(text, renderer.render(input));
+
+    let runs = |line: usize| {
+        groups[0].lines[line]
+            .iter()
+            .map(|run| (run.text.as_str(), run.style))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        runs(0),
+        [
+            ("warning", SemanticStyle::Level(Severity::Warning)),
+            (": unused variable: `x`", SemanticStyle::MainHeaderMsg),
+        ]
+    );
+    assert_eq!(
+        runs(3),
+        [
+            ("1", SemanticStyle::LineNumber),
+            (" ", SemanticStyle::NoStyle),
+            ("|", SemanticStyle::LineNumber),
+            (" ", SemanticStyle::NoStyle),
+            ("let x = 1;", SemanticStyle::Quotation),
+        ]
+    );
+    assert_eq!(
+        runs(4),
+        [
+            ("  ", SemanticStyle::NoStyle),
+            ("|", SemanticStyle::LineNumber),
+            ("     ", SemanticStyle::NoStyle),
+            ("^", SemanticStyle::UnderlinePrimary),
+            (" ", SemanticStyle::NoStyle),
+            ("unused", SemanticStyle::LabelPrimary),
+        ]
+    );
+}
+
+#[test]
+fn measure() {
+    use unicode_width::UnicodeWidthStr as _;
+
+    let source = "let 名前 = 1;\nlet y = 名前 + 1;";
+    let input = &[
+        Level::ERROR.primary_title("mismatched types").element(
+            Snippet::source(source)
+                .path("src/lib.rs")
+                .annotation(AnnotationKind::Primary.span(24..34).label("expected `u8`")),
+        ),
+        Group::with_title(Level::HELP.secondary_title("change the type of the binding")).element(
+            Snippet::source(source)
+                .path("src/lib.rs")
+                .patch(Patch::new(10..10, ": u8")),
+        ),
+        Group::with_title(Level::NOTE.secondary_title("\x1b[1mbold\x1b[0m title")),
+    ];
+
+    for renderer in [
+        Renderer::plain(),
+        Renderer::styled().decor_style(DecorStyle::Unicode),
+        Renderer::plain().short_message(true),
+        Renderer::plain().term_width(20),
+    ] {
+        let sizes = renderer.measure(input);
+        let rendered = renderer.render(input);
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(sizes.iter().map(|s| s.rows).sum::<usize>(), lines.len());
+
+        let mut lines = lines.into_iter();
+        for size in sizes {
+            let columns = lines
+                .by_ref()
+                .take(size.rows)
+                .map(|line| anstream::adapter::strip_str(line).to_string().width())
+                .max()
+                .unwrap_or(0);
+            assert_eq!(size.columns, columns, "{rendered}");
+        }
+    }
+
+    let sizes = Renderer::plain().measure(input);
+    let sizes = sizes
+        .iter()
+        .map(|s| (s.rows, s.columns))
+        .collect::<Vec<_>>();
+    assert_eq!(sizes, [(6, 34), (4, 36), (1, 16)]);
+}
+
+#[test]
+fn color_support() {
+    let source = "let x: u8 = 256;";
+    let input = &[Level::ERROR.primary_title("literal out of range").element(
+        Snippet::source(source)
+            .path("src/lib.rs")
+            .annotation(AnnotationKind::Primary.span(12..15).label("out of range"))
+            .annotation(AnnotationKind::Context.span(7..9).label("for this type")),
+    )];
+
+    let renderer = Renderer::styled()
+        .error(RgbColor(250, 80, 80).on_default().effects(Effects::BOLD))
+        .context(Ansi256Color(63).on_default().underline());
+    assert_eq!(
+        renderer
+            .clone()
+            .color_support(ColorSupport::TrueColor)
+            .render(input),
+        renderer.render(input)
+    );
+    assert_eq!(
+        renderer
+            .clone()
+            .color_support(ColorSupport::Ansi256)
+            .render(input),
+        renderer
+            .clone()
+            .error(Ansi256Color(203).on_default().effects(Effects::BOLD))
+            .render(input)
+    );
+    assert_eq!(
+        renderer
+            .clone()
+            .color_support(ColorSupport::Ansi16)
+            .render(input),
+        renderer
+            .clone()
+            .error(AnsiColor::BrightRed.on_default().effects(Effects::BOLD))
+            .context(AnsiColor::BrightBlue.on_default().underline())
+            .render(input)
+    );
+
+    let rendered = renderer
+        .theme(Theme::HIGH_CONTRAST)
+        .color_support(ColorSupport::Monochrome)
+        .render(input);
+    assert_eq!(
+        anstream::adapter::strip_str(&rendered).to_string(),
+        Renderer::plain().render(input)
+    );
+    let escapes = rendered.split('\x1b').skip(1);
+    for escape in escapes {
+        let code = &escape[..escape.find('m').unwrap() + 1];
+        assert!(["[0m", "[1m", "[4m"].contains(&code), "{code:?}");
+    }
+}
+
+#[test]
+fn theme() {
+    let input = &[Level::WARNING.primary_title("unused variable").element(
+        Snippet::source("let x = 1;")
+            .path("src/lib.rs")
+            .annotation(AnnotationKind::Primary.span(4..5)),
+    )];
+
+    assert_eq!(
+        Renderer::styled().theme(Theme::DEFAULT).render(input),
+        Renderer::styled().render(input)
+    );
+    for theme in [
+        Theme::LIGHT,
+        Theme::DARK,
+        Theme::HIGH_CONTRAST,
+        Theme::COLOR_BLIND,
+    ] {
+        let rendered = Renderer::styled().theme(theme).render(input);
+        assert_ne!(rendered, Renderer::styled().render(input));
+        assert_eq!(
+            anstream::adapter::strip_str(&rendered).to_string(),
+            Renderer::plain().render(input)
+        );
+    }
+}